@@ -2,9 +2,10 @@ use crate::{
     backend::Backend,
     frontend::Frontend,
     utils::{
-        vec2, RenderSpace, ScreenSpace, Vec2, FRAME_TIME_MS, KEY_COOLDOWN_MS,
-        MOUSE_DRAG_THRESHOLD_PX, MOUSE_HOLD_THRESHOLD_MS, MOUSE_PRESS_COOLDOWN_MS, MS_BUFFER,
-        SIM_MAX_SCALE, TARGET_FPS,
+        vec2, RenderSpace, ScreenSpace, Vec2, FRAME_TIME_MS, KEY_COOLDOWN_MS, MAX_FRAME_TIME,
+        MAX_STEPS_PER_FRAME, MOUSE_DRAG_THRESHOLD_PX, MOUSE_HOLD_THRESHOLD_MS,
+        MOUSE_PRESS_COOLDOWN_MS, MS_BUFFER, SCROLL_PIXELS_PER_LINE, SIM_MAX_SCALE, SIM_TIMESTEP,
+        TARGET_FPS,
     },
 };
 use educe::Educe;
@@ -15,7 +16,7 @@ use std::{
 };
 use winit::{
     dpi::PhysicalSize,
-    event::{ElementState, Event, KeyEvent, MouseButton, WindowEvent},
+    event::{ElementState, Event, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
     event_loop::{EventLoop, EventLoopWindowTarget},
     keyboard::{KeyCode, PhysicalKey},
     window::{Window, WindowBuilder},
@@ -42,6 +43,8 @@ pub struct InputData {
     // this records the release event, holding its current state, the time of release and pos of release
     // this is currently (13/11) used for the gravity_sim angry birds particle fire!
     pub mouse_released: MouseInput, // records an event's current state, with timestamp of press
+    pub scroll: f64, // wheel lines scrolled this frame, positive is away from the user.
+    pub right_click: Option<Vec2<f64, ScreenSpace>>, // right button pressed this frame, at this position.
 
     // TODO(TOM): should keys_held have a cooldown?
     // both fields have a tap_cooldown, however "keys_tapped is reset each frame"
@@ -148,6 +151,8 @@ impl<'a, F: Frontend + std::fmt::Debug + 'a> App<'a, F> {
                     pos: vec2(0.0, 0.0),
                     time: Instant::now(),
                 },
+                scroll: 0.0,
+                right_click: None,
                 keys_held: [false; 256],
                 keys_pressed: [false; 256],
                 tap_cooldowns: [Instant::now(); 256],
@@ -159,7 +164,8 @@ impl<'a, F: Frontend + std::fmt::Debug + 'a> App<'a, F> {
     pub fn run(mut self) {
         let start = Instant::now();
         let mut frame_timer = start;
-        let mut avg_frame_time = Duration::from_millis(FRAME_TIME_MS as u64);
+        let mut last_step_time = start;
+        let mut accumulator = Duration::ZERO;
 
         self.event_loop
             .run(move |event, control_flow| match event {
@@ -203,6 +209,19 @@ impl<'a, F: Frontend + std::fmt::Debug + 'a> App<'a, F> {
                             self.inputs.mouse_down = false;
                         }
                     },
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Right,
+                        ..
+                    } => {
+                        self.inputs.right_click = Some(self.inputs.mouse_pos);
+                    }
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        self.inputs.scroll += match *delta {
+                            MouseScrollDelta::LineDelta(_, y) => f64::from(y),
+                            MouseScrollDelta::PixelDelta(pos) => pos.y / SCROLL_PIXELS_PER_LINE,
+                        };
+                    }
                     WindowEvent::CursorMoved { position, .. } => {
                         self.inputs.mouse_pos = vec2(position.x, position.y);
                     }
@@ -230,14 +249,28 @@ impl<'a, F: Frontend + std::fmt::Debug + 'a> App<'a, F> {
                             &mut self.inputs,
                         );
 
-                        self.frontend.update(&mut self.inputs, avg_frame_time);
+                        self.frontend.handle_inputs(&mut self.inputs);
+
+                        let alpha = Self::step_fixed(
+                            &mut self.frontend,
+                            &mut accumulator,
+                            &mut last_step_time,
+                        );
+                        if self.frontend.is_deterministic() {
+                            trace!(
+                                "Frame {}: state hash {:016x}",
+                                self.frontend.get_sim_data().frame,
+                                self.frontend.state_hash()
+                            );
+                        }
+                        self.frontend.render(&mut self.inputs, alpha);
 
                         Self::clear_inputs(&mut self.inputs);
 
                         let sim_data = self.frontend.get_sim_data();
                         self.backend.render(&sim_data, start);
 
-                        let avg_frame_time = Self::timing(sim_data.frame, start, &mut frame_timer);
+                        Self::timing(sim_data.frame, start, &mut frame_timer);
                     }
                     _ => {}
                 },
@@ -295,15 +328,58 @@ impl<'a, F: Frontend + std::fmt::Debug + 'a> App<'a, F> {
         }
     }
 
+    // Fixed timestep accumulator, the sim always advances in SIM_TIMESTEP chunks regardless of
+    // render rate. Returns how far between the last two steps the current frame sits (0..1).
+    fn step_fixed(
+        frontend: &mut F,
+        accumulator: &mut Duration,
+        last_step_time: &mut Instant,
+    ) -> f64 {
+        optick::event!("App::step_fixed");
+
+        // wall clock timing varies run to run, lockstep keeps frame N at step N.
+        if frontend.is_deterministic() {
+            frontend.step(SIM_TIMESTEP);
+            *accumulator = Duration::ZERO;
+            *last_step_time = Instant::now();
+            return 1.0;
+        }
+
+        // clamped so a long stall (window drag, breakpoint) doesn't try to catch up all at once.
+        *accumulator += last_step_time.elapsed().min(MAX_FRAME_TIME);
+        *last_step_time = Instant::now();
+
+        let mut steps = 0;
+        while *accumulator >= SIM_TIMESTEP && steps < MAX_STEPS_PER_FRAME {
+            frontend.step(SIM_TIMESTEP);
+            *accumulator -= SIM_TIMESTEP;
+            steps += 1;
+        }
+
+        // Spiral of death, steps are slower than real time. Drop the backlog rather than falling further behind.
+        if *accumulator >= SIM_TIMESTEP {
+            trace!(
+                "Sim falling behind, dropping {:.2?} of sim time",
+                *accumulator
+            );
+            *accumulator =
+                Duration::from_nanos((accumulator.as_nanos() % SIM_TIMESTEP.as_nanos()) as u64);
+        }
+
+        accumulator.as_secs_f64() / SIM_TIMESTEP.as_secs_f64()
+    }
+
     fn clear_inputs(inputs: &mut InputData) {
         // Mouse held is bound by press,release events, these are not.
         inputs.mouse_pressed.state = false;
         inputs.mouse_released.state = false;
+        inputs.scroll = 0.0;
+        inputs.right_click = None;
         inputs.keys_pressed = [false; 256];
     }
 
     // TODO(TOM): instead of sleeping, have multiple frames in flight, prob max 2 (front & back buffer)
-    fn timing(frame: usize, start: Instant, frame_timer: &mut Instant) -> Duration {
+    fn timing(frame: usize, start: Instant, frame_timer: &mut Instant) {
         optick::event!("App::timing");
 
         let elapsed = frame_timer.elapsed();
@@ -320,7 +396,5 @@ impl<'a, F: Frontend + std::fmt::Debug + 'a> App<'a, F> {
             std::thread::sleep(Duration::from_millis(with_buffer as u64));
         }
         *frame_timer = Instant::now();
-
-        return avg_frame_time;
     }
 }
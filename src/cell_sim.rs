@@ -1,4 +1,4 @@
-use std::{mem::transmute, time::Duration};
+use std::{hash::Hasher, mem::transmute, time::Duration};
 
 use crate::{
     app::InputData,
@@ -6,6 +6,7 @@ use crate::{
     utils::*,
 };
 use log::{info, trace};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use winit::{dpi::Pixel, keyboard::KeyCode};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -41,6 +42,8 @@ struct State {
     running: bool,
     step_sim: bool,
     mouse: Vec2<f64, ScreenSpace>,
+    seed: u64,
+    deterministic: bool, // lockstep stepping, for replays.
 }
 
 #[derive(Debug, Clone)]
@@ -125,21 +128,38 @@ impl Frontend for CellSim {
     }
     // endregion
     // region: update
-    fn update(&mut self, inputs: &mut InputData, _avg_frame_time: Duration) {
+    fn handle_inputs(&mut self, inputs: &mut InputData) {
         self.handle_input_state(inputs);
+    }
 
+    fn step(&mut self, _dt: Duration) {
         if self.state.running || self.state.step_sim {
             self.update_gol();
+            // applied per step, otherwise a second step in the same frame reads stale cells.
+            self.apply_cell_updates();
+            self.state.step_sim = false;
         }
+    }
 
-        for y in 1..self.sim_size.y - 1 {
-            for x in 1..self.sim_size.x - 1 {
-                let cell = self.get_cell(vec2(x, y));
-                if cell.updated {
-                    self.update_cell(vec2(x, y), cell.mat_to);
-                }
-            }
+    fn state_hash(&self) -> u64 {
+        let mut hasher = StateHasher::new();
+        hasher.write_u32(self.sim_size.x as u32);
+        hasher.write_u32(self.sim_size.y as u32);
+        for cell in &self.sim_buf {
+            hasher.write_u8(cell.mat as u8);
+            hasher.write_u8(cell.mat_to as u8);
+            hasher.write_u8(cell.updated as u8);
         }
+        hasher.finish()
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.state.deterministic
+    }
+
+    fn render(&mut self, _inputs: &mut InputData, _alpha: f64) {
+        // cells drawn by the mouse this frame.
+        self.apply_cell_updates();
 
         // TODO(TOM): this will work for cellular automata (ish), but not for particles
         // particles
@@ -162,7 +182,6 @@ impl Frontend for CellSim {
         self.render_mouse_outline(WHITE);
 
         self.prev_state = self.state;
-        self.state.step_sim = false;
         self.state.frame += 1;
     }
     // endregion
@@ -217,8 +236,31 @@ impl CellSim {
         pos.x >= self.sim_size.x || pos.y >= self.sim_size.y
     }
 
+    fn apply_cell_updates(&mut self) {
+        for y in 1..self.sim_size.y - 1 {
+            for x in 1..self.sim_size.x - 1 {
+                let cell = self.get_cell(vec2(x, y));
+                if cell.updated {
+                    self.update_cell(vec2(x, y), cell.mat_to);
+                }
+            }
+        }
+    }
+
+    // Random soup from the current seed, the same seed & size always give the same soup.
     fn reset_sim(&mut self) {
-        todo!("cell_sim::reset_sim")
+        let mut rng = StdRng::seed_from_u64(self.state.seed);
+        for y in 0..self.sim_size.y {
+            for x in 0..self.sim_size.x {
+                let mat = if rng.gen_bool(SOUP_DENSITY) {
+                    Material::Alive
+                } else {
+                    Material::Dead
+                };
+                self.update_cell(vec2(x, y), mat);
+            }
+        }
+        info!("Soup seed: {}", self.state.seed);
     }
 
     fn clear_sim(&mut self) {
@@ -282,15 +324,33 @@ impl CellSim {
             self.state.running = !self.state.running;
             info!("Sim running: {}", self.state.running);
         }
-        self.state.step_sim = inputs.is_pressed(KeyCode::ArrowRight) && !self.state.running;
+        self.state.step_sim |= inputs.is_pressed(KeyCode::ArrowRight) && !self.state.running;
 
-        // Clear Sim on KeyC
+        // Clear Sim on KeyC, random soup on KeyR
         if inputs.is_pressed(KeyCode::KeyC) {
             self.clear_sim();
         } else if inputs.is_pressed(KeyCode::KeyR) {
+            // Shift+R re-rolls the soup with the next seed.
+            if inputs.is_held(KeyCode::ShiftLeft) {
+                self.state.seed = self.state.seed.wrapping_add(1);
+            }
             self.reset_sim();
         }
 
+        // Toggle deterministic mode on KeyZ, Shift+Z logs the current state hash
+        if inputs.is_pressed(KeyCode::KeyZ) {
+            if inputs.is_held(KeyCode::ShiftLeft) {
+                info!(
+                    "Frame {}: state hash {:016x}",
+                    self.state.frame,
+                    self.state_hash()
+                );
+            } else {
+                self.state.deterministic = !self.state.deterministic;
+                info!("Deterministic: {}", self.state.deterministic);
+            }
+        }
+
         // Branchless Draw Size Change
         self.state.draw_size += inputs.is_pressed(KeyCode::ArrowUp) as i32;
         self.state.draw_size -= inputs.is_pressed(KeyCode::ArrowDown) as i32;
@@ -419,6 +479,8 @@ impl CellSim {
             step_sim: false,
             scale,
             mouse: vec2(0.0, 0.0),
+            seed: INIT_SEED,
+            deterministic: false,
         };
 
         Self {
@@ -20,5 +20,15 @@ pub trait Frontend {
     fn resize_sim(&mut self, window_size: Vec2<u32, ScreenSpace>);
     fn rescale_sim(&mut self, scale: u32);
 
-    fn update(&mut self, inputs: &mut InputData, avg_frame_time: Duration);
+    // Called once per rendered frame, before any simulation steps.
+    fn handle_inputs(&mut self, inputs: &mut InputData);
+    // Advances the simulation by exactly one fixed timestep, may run 0..N times per frame.
+    fn step(&mut self, dt: Duration);
+    // alpha is how far (0..1) the render time sits between the last two steps, for interpolation.
+    fn render(&mut self, inputs: &mut InputData, alpha: f64);
+
+    // Hash of everything the steps evolve, equal hashes mean bit-identical state.
+    fn state_hash(&self) -> u64;
+    // Lockstep, exactly one step per frame, so replaying the same inputs replays the same state.
+    fn is_deterministic(&self) -> bool;
 }
@@ -1,27 +1,71 @@
+mod barnes_hut;
+mod boundary;
+mod broad_phase;
+mod camera;
+mod colour_map;
+mod constraints;
+mod diagnostics;
+mod emitters;
+mod force_fields;
+mod forces;
+mod grab;
+mod inspector;
+mod integrator;
+mod motion;
+mod particles;
+mod prediction;
+mod scenarios;
+mod softening;
+mod trails;
+
 use crate::{
     app::InputData,
     frontend::{Frontend, SimData},
     utils::*,
 };
+use barnes_hut::QuadTree;
+use boundary::{Boundary, Bounds};
+use broad_phase::BroadPhase;
+use camera::{Camera, Follow};
+use colour_map::ColourMap;
+use constraints::{Brush, Constraints};
 use core::f64;
+use diagnostics::{Conservation, Diagnostics};
 use educe::Educe;
-use log::{info, trace};
+use emitters::Emitter;
+use force_fields::{FieldKind, ForceField};
+use forces::ForceModel;
+use grab::Grab;
+use integrator::{AccelerationKey, Integrator};
+use log::{info, trace, warn};
+use motion::{Motion, Path, SpawnMotion};
 use num::pow::Pow;
+use particles::{for_each_mut, Particles};
+use prediction::{Background, PredictionEnd};
 use rayon::{prelude::*, vec};
+use scenarios::Scenario;
+use soa_rs::Soars;
+use softening::{Gravity, Softening};
 use std::{
     f32::EPSILON,
+    hash::Hasher,
     mem::transmute,
-    ops::{Add, Div, Mul, Sub},
+    ops::{Div, Mul, Sub},
     time::{Duration, Instant},
 };
+use trails::Trails;
 use winit::keyboard::KeyCode;
 
-#[derive(Educe, Clone, Copy)]
+#[derive(Educe, Clone, Copy, Soars)]
 #[educe(Debug)]
+#[soa_derive(Debug)]
 struct Particle {
+    id: u64, // stable across culling & reordering, assigned by the simulation.
     #[educe(Debug(method(fmt_limited_precision)))]
     pos: Vec2<f64, WorldSpace>,
     #[educe(Debug(method(fmt_limited_precision)))]
+    prev_pos: Vec2<f64, WorldSpace>, // position at the start of the last step, for render interpolation.
+    #[educe(Debug(method(fmt_limited_precision)))]
     vel: Vec2<f64, WorldSpace>,
     #[educe(Debug(method(fmt_limited_precision)))]
     acc: Vec2<f64, WorldSpace>,
@@ -31,11 +75,73 @@ struct Particle {
     mass: f64,
     #[educe(Debug(method(fmt_limited_precision)))]
     radius: f64,
+    collisions: u32, // contacts resolved, merges add up both bodies' counts.
+    charge: f64,     // mass units, see ForceModel.
+    motion: Motion,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ForceSolver {
+    BruteForce, // exact O(n^2) pair loop
+    BarnesHut,  // O(n log n) quadtree approximation, accuracy set by theta
+}
+
+impl ForceSolver {
+    const fn next(self) -> Self {
+        match self {
+            Self::BruteForce => Self::BarnesHut,
+            Self::BarnesHut => Self::BruteForce,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CollisionMode {
+    Bounce,    // impulse scaled by restitution (1.0 = elastic), with optional friction.
+    Inelastic, // particles stick, leaving with a shared velocity.
+    Merge,     // the heavier (or a pinned / scripted) particle absorbs the other.
+}
+
+impl CollisionMode {
+    const fn next(self) -> Self {
+        match self {
+            Self::Bounce => Self::Inelastic,
+            Self::Inelastic => Self::Merge,
+            Self::Merge => Self::Bounce,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 struct Simulation {
-    particles: Vec<SyncCell<Particle>>,
+    particles: Particles,
+    next_id: u64,
+    solver: ForceSolver,
+    forces: ForceModel,
+    force_fields: Vec<ForceField>,
+    emitters: Vec<Emitter>,
+    constraints: Constraints,
+    acc_key: Option<AccelerationKey>, // what `acc` was last evaluated with.
+    theta: f64,                       // Barnes-Hut opening angle
+    integrator: Integrator,
+    collision_mode: CollisionMode,
+    restitution: f64,
+    friction: f64,
+    broad_phase: BroadPhase,
+    step: usize,
+    diagnostics: Diagnostics,
+    units: UnitSystem,
+    softening: Softening,
+    softening_length: f64,
+    substep_encounters: bool, // split steps on close encounters, see encounter_substeps.
+    quarantined: Vec<Particle>, // went non-finite, pulled out before they poison everything else, newest last.
+    parallel: bool,             // rayon force & integration passes, bit-identical to serial.
+    deterministic: bool,        // serial passes & lockstep stepping, for replays.
+    boundary: Boundary,
+    bounds: Bounds,
+    escape_radius: f64, // from the centre of bounds, open boundary only.
+    scenario: Scenario,
+    seed: u64,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -47,6 +153,11 @@ struct FrontendState {
     running: bool,
     step_sim: bool,
     mouse: Vec2<f64, ScreenSpace>,
+    prediction_steps: usize,
+    spawn_motion: SpawnMotion,
+    spawn_charge: f64, // per unit mass.
+    brush: Brush,
+    lattice_size: usize, // particles along each side of a brushed lattice.
 }
 
 #[derive(Educe, Clone)]
@@ -58,8 +169,7 @@ pub struct GravitySim {
 
     window_size: Vec2<i32, ScreenSpace>,
     sim_size: Vec2<i32, RenderSpace>,
-    camera: Vec2<f64, WorldSpace>, // describes the top left of the viewport.
-    camera_vel: Vec2<f64, WorldSpace>,
+    camera: Camera,
 
     #[educe(Debug(ignore))]
     bufs: [Vec<SyncCell<u8>>; 2],
@@ -67,6 +177,13 @@ pub struct GravitySim {
 
     #[educe(Debug(ignore))]
     simulation: Simulation,
+    selected: Option<u64>, // particle id, shown in the inspector.
+    grab: Option<Grab>,
+    #[educe(Debug(ignore))]
+    prediction: Option<Background>, // recorded when a drag starts, dropped when it ends.
+    #[educe(Debug(ignore))]
+    trails: Trails,
+    colour_map: ColourMap,
     // particles: Vec<SyncCell<Particle>>,
 }
 
@@ -129,23 +246,48 @@ impl Frontend for GravitySim {
     }
     // endregion
     // region: Update
-    fn update(&mut self, inputs: &mut InputData, delta_time: Duration) {
-        optick::event!("GravitySim::update");
+    fn handle_inputs(&mut self, inputs: &mut InputData) {
+        optick::event!("GravitySim::handle_inputs");
 
         self.handle_input_state(inputs);
+    }
 
-        self.clear_buffer(self.front_buffer, 44);
+    fn step(&mut self, dt: Duration) {
+        optick::event!("GravitySim::step");
 
         if self.state.running || self.state.step_sim {
-            self.simulation.update(delta_time.as_secs_f64());
+            self.simulation.update(dt.as_secs_f64());
+            self.state.step_sim = false;
         }
+    }
+
+    fn render(&mut self, inputs: &mut InputData, alpha: f64) {
+        optick::event!("GravitySim::render");
+
+        self.clear_buffer(self.front_buffer, 44);
 
+        // paused, nothing to interpolate towards.
+        let alpha = if self.state.running { alpha } else { 1.0 };
+        self.camera
+            .track(self.simulation.get_particles(), alpha, self.sim_size);
+        // under the particles, so bodies always sit on top of their own trail.
+        self.trails.record(self.simulation.get_particles(), alpha);
+        self.render_trails();
+        self.render_links(alpha);
         Self::render_particles(
             &self.bufs[self.front_buffer],
             self.simulation.get_particles(),
+            &self.colour_map.colours(self.simulation.get_particles()),
             self.sim_size,
             self.camera,
+            alpha,
         );
+        self.render_selection(alpha);
+        self.render_force_fields();
+        self.render_emitters();
+        if self.simulation.boundary != Boundary::Open {
+            self.render_bounds();
+        }
 
         self.handle_input_renders(inputs);
 
@@ -154,18 +296,122 @@ impl Frontend for GravitySim {
         }
 
         self.prev_state = self.state;
-        self.state.step_sim = false;
         self.state.frame += 1;
 
         //TODO(TOM): sort out & use for multiple frames in flight.
         // self.front_buffer = (self.front_buffer + 1) % 2;
     }
+
+    fn state_hash(&self) -> u64 {
+        self.simulation.state_hash()
+    }
+
+    fn is_deterministic(&self) -> bool {
+        self.simulation.deterministic
+    }
     // endregion
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////
 
 impl GravitySim {
+    // TODO(TOM): vary with current scale factor.
+    fn launch_velocity(
+        &self,
+        pressed: Vec2<f64, ScreenSpace>,
+        released: Vec2<f64, ScreenSpace>,
+    ) -> Vec2<f64, WorldSpace> {
+        let drag = pressed
+            .sub(released)
+            .scale(self.state.scale)
+            .div(self.sim_size.cast())
+            .mul(MOUSE_DRAWBACK_MULTIPLIER)
+            .div(self.camera.zoom)
+            .cast_unit();
+        self.camera.to_world_direction(drag)
+    }
+
+    fn mouse_to_world(&self, mouse: Vec2<f64, ScreenSpace>) -> Vec2<f64, WorldSpace> {
+        self.camera.to_world(mouse.scale(self.state.scale))
+    }
+
+    // Nearest particle, as long as the cursor is within SELECT_RADIUS_PX of its edge.
+    fn particle_under(&self, mouse: Vec2<f64, ScreenSpace>) -> Option<u64> {
+        let world = self.mouse_to_world(mouse);
+        self.simulation.nearest_particle(world).filter(|&id| {
+            let p = self
+                .simulation
+                .particles
+                .get(self.simulation.index_of(id).unwrap());
+            ((p.pos - world).length() - p.radius) * self.camera.zoom <= SELECT_RADIUS_PX
+        })
+    }
+
+    // draw size is in pixels, so new particles come out the size of the cursor at any zoom.
+    // The brush is sized in default units, a scenario in bigger units gets a body of the same
+    // size in metres, rather than e.g. an earth-density ball with an 8e9 m radius.
+    fn spawn_radius(&self) -> f64 {
+        let units = self.simulation.units;
+        let radius = self.state.draw_size as f64 / self.camera.zoom;
+        units.distance_from_si(DEFAULT_UNITS.distance_to_si(radius))
+    }
+
+    fn view_centre(&self) -> Vec2<f64, WorldSpace> {
+        self.camera.centre(self.sim_size)
+    }
+
+    // Ids start from 0 again after a clear or reset, anything holding on to one would latch onto
+    // an unrelated new particle.
+    fn forget_particles(&mut self) {
+        self.trails.clear();
+        self.selected = None;
+        self.grab = None;
+        if matches!(
+            self.camera.follow,
+            Follow::Particle(_) | Follow::CoRotating(..)
+        ) {
+            self.camera.set_follow(Follow::Free, self.sim_size);
+        }
+    }
+
+    fn spawn(&mut self, pos: Vec2<f64, WorldSpace>, vel: Vec2<f64, WorldSpace>) {
+        if self.state.brush != Brush::Particle {
+            self.simulation.spawn_lattice(
+                pos,
+                vel,
+                self.spawn_radius(),
+                self.state.lattice_size,
+                self.state.brush,
+            );
+            return;
+        }
+        self.simulation.spawn_particle(
+            pos,
+            vel,
+            self.spawn_radius(),
+            self.spawn_motion(pos, vel),
+            self.state.spawn_charge,
+        );
+    }
+
+    fn spawn_motion(&self, pos: Vec2<f64, WorldSpace>, vel: Vec2<f64, WorldSpace>) -> Motion {
+        match self.state.spawn_motion {
+            SpawnMotion::Dynamic => Motion::Dynamic,
+            SpawnMotion::Static => Motion::Static,
+            SpawnMotion::Line => Motion::Kinematic(Path::Line),
+            SpawnMotion::Orbit => {
+                let centre = self
+                    .selected
+                    .and_then(|id| self.simulation.index_of(id))
+                    .map_or_else(
+                        || self.view_centre(),
+                        |i| self.simulation.particles.pos()[i],
+                    );
+                Motion::orbit(centre, pos, vel)
+            }
+        }
+    }
+
     fn write_colour(index: usize, buf: &[SyncCell<u8>], col: Rgba) {
         *buf[index + 0].get_mut() = col.r;
         *buf[index + 1].get_mut() = col.g;
@@ -179,6 +425,16 @@ impl GravitySim {
         Self::write_colour(index, buf, col);
     }
 
+    // Like write_to_buf, but anything off screen is dropped. Only needs &self, so it can be
+    // called while the simulation is borrowed.
+    fn plot_clipped(&self, pos: Vec2<i32, RenderSpace>, col: Rgba) {
+        let size = self.sim_size;
+        if !(pos.x < 0 || pos.y < 0 || pos.x >= size.x || pos.y >= size.y) {
+            let index = 4 * (pos.y * size.x + pos.x) as usize;
+            Self::write_colour(index, &self.bufs[self.front_buffer], col);
+        }
+    }
+
     fn clear_buffer(&mut self, buffer: usize, val: u8) {
         optick::event!("Resetting texture");
         let buf_ptr = self.bufs[self.front_buffer].as_mut_ptr();
@@ -196,26 +452,16 @@ impl GravitySim {
         let shift_modifier = inputs.is_held(KeyCode::ShiftLeft) as i32;
         let pressed = inputs.mouse_pressed.pos;
         let released = inputs.mouse_released.pos;
-        let mouse_pos_world = pressed.scale(self.state.scale).cast_unit().add(self.camera);
-        if inputs.was_mouse_dragging() {
-            // Draws particle at initial position, give it velocity based on drag distance.
-            let pressed_world = pressed.scale(self.state.scale).cast_unit().add(self.camera);
-            let game_pos_delta = pressed.sub(released).scale(self.state.scale);
-
-            // TODO(TOM): vary with current scale factor.
-            let velocity = game_pos_delta
-                .div(self.sim_size.cast())
-                .mul(MOUSE_DRAWBACK_MULTIPLIER)
-                .cast_unit();
-
-            self.simulation
-                .spawn_particle(mouse_pos_world, velocity, self.state.draw_size as f64);
-        } else if inputs.was_mouse_pressed() {
-            self.simulation.spawn_particle(
-                mouse_pos_world,
-                vec2(0.0, 0.0),
-                self.state.draw_size as f64,
-            );
+        let mouse_pos_world = self.mouse_to_world(pressed);
+        // Grab & throw on a particle, spawn anywhere else
+        if !self.update_grab(inputs) {
+            if inputs.was_mouse_dragging() {
+                // Draws particle at initial position, give it velocity based on drag distance.
+                let velocity = self.launch_velocity(pressed, released);
+                self.spawn(mouse_pos_world, velocity);
+            } else if inputs.was_mouse_pressed() {
+                self.spawn(mouse_pos_world, vec2(0.0, 0.0));
+            }
         }
 
         // Toggle simulation on KeySpace
@@ -223,20 +469,309 @@ impl GravitySim {
             self.state.running = !self.state.running;
             info!("Sim running: {}", self.state.running);
         }
-        self.state.step_sim = inputs.is_pressed(KeyCode::ArrowRight);
+        // held until the next fixed step consumes it, a frame may not contain a step.
+        self.state.step_sim |= inputs.is_pressed(KeyCode::ArrowRight);
 
         // Clear Sim on KeyC
         if inputs.is_pressed(KeyCode::KeyC) {
             self.simulation.clear();
+            self.forget_particles();
         } else if inputs.is_pressed(KeyCode::KeyR) {
-            self.simulation.reset();
+            // Shift+R re-rolls the random scenarios with the next seed.
+            if shift_modifier != 0 {
+                self.simulation.seed = self.simulation.seed.wrapping_add(1);
+            }
+            self.simulation.reset(self.view_centre());
+            self.forget_particles();
+        }
+        // Load scenario presets on Digit1..Digit8
+        const SCENARIO_KEYS: [KeyCode; Scenario::ALL.len()] = [
+            KeyCode::Digit1,
+            KeyCode::Digit2,
+            KeyCode::Digit3,
+            KeyCode::Digit4,
+            KeyCode::Digit5,
+            KeyCode::Digit6,
+            KeyCode::Digit7,
+            KeyCode::Digit8,
+        ];
+        for (key, scenario) in SCENARIO_KEYS.into_iter().zip(Scenario::ALL) {
+            if inputs.is_pressed(key) {
+                self.simulation.scenario = scenario;
+                self.simulation.reset(self.view_centre());
+                self.forget_particles();
+            }
+        }
+
+        // Cycle force solver on KeyB, Barnes-Hut opening angle on BracketLeft/BracketRight
+        if inputs.is_pressed(KeyCode::KeyB) {
+            self.simulation.solver = self.simulation.solver.next();
+            info!("Force solver: {:?}", self.simulation.solver);
+        }
+        if inputs.is_pressed(KeyCode::BracketLeft) || inputs.is_pressed(KeyCode::BracketRight) {
+            let step = if inputs.is_pressed(KeyCode::BracketLeft) {
+                -BARNES_HUT_THETA_STEP
+            } else {
+                BARNES_HUT_THETA_STEP
+            };
+            self.simulation.theta = (self.simulation.theta + step).clamp(0.0, BARNES_HUT_MAX_THETA);
+            info!("Barnes-Hut theta: {:.2}", self.simulation.theta);
+        }
+        // Cycle integrator on KeyI
+        if inputs.is_pressed(KeyCode::KeyI) {
+            self.simulation.integrator = self.simulation.integrator.next();
+            info!("Integrator: {:?}", self.simulation.integrator);
+        }
+        // Cycle collision mode on KeyM, restitution on Comma/Period,
+        // Shift+M toggles friction on bounces
+        if inputs.is_pressed(KeyCode::KeyM) {
+            if shift_modifier != 0 {
+                self.simulation.friction = if self.simulation.friction > 0.0 {
+                    0.0
+                } else {
+                    COLLISION_FRICTION
+                };
+                info!("Friction: {:.2}", self.simulation.friction);
+            } else {
+                self.simulation.collision_mode = self.simulation.collision_mode.next();
+                info!("Collision mode: {:?}", self.simulation.collision_mode);
+            }
+        }
+        if inputs.is_pressed(KeyCode::Comma) || inputs.is_pressed(KeyCode::Period) {
+            let step = if inputs.is_pressed(KeyCode::Comma) {
+                -RESTITUTION_STEP
+            } else {
+                RESTITUTION_STEP
+            };
+            self.simulation.restitution = (self.simulation.restitution + step).clamp(0.0, 1.0);
+            info!("Restitution: {:.2}", self.simulation.restitution);
+        }
+        // Cycle collision broad phase on KeyH
+        if inputs.is_pressed(KeyCode::KeyH) {
+            self.simulation.broad_phase = self.simulation.broad_phase.next();
+            info!("Broad phase: {:?}", self.simulation.broad_phase);
+        }
+        // Toggle conservation diagnostics on KeyL, logged every DIAGNOSTICS_LOG_INTERVAL steps,
+        // Shift+L summarises the recorded history
+        if inputs.is_pressed(KeyCode::KeyL) {
+            if shift_modifier != 0 {
+                self.log_diagnostics_history();
+            } else {
+                self.simulation.diagnostics.enabled = !self.simulation.diagnostics.enabled;
+                self.simulation.diagnostics.reset();
+                info!("Diagnostics: {}", self.simulation.diagnostics.enabled);
+            }
+        }
+        // Cycle gravitational softening on KeyG, softening length on Semicolon/Quote,
+        // Shift+G logs the particles quarantined for going non-finite
+        if inputs.is_pressed(KeyCode::KeyG) {
+            if shift_modifier != 0 {
+                let quarantined = &self.simulation.quarantined;
+                info!("Quarantined: {} particle(s)", quarantined.len());
+                for p in quarantined {
+                    info!(
+                        "Particle {}: pos {:?} vel {:?} mass {:?}",
+                        p.id, p.pos, p.vel, p.mass
+                    );
+                }
+            } else {
+                self.simulation.softening = self.simulation.softening.next();
+                info!("Softening: {:?}", self.simulation.softening);
+            }
+        }
+        if inputs.is_pressed(KeyCode::Semicolon) || inputs.is_pressed(KeyCode::Quote) {
+            let step = if inputs.is_pressed(KeyCode::Semicolon) {
+                -SOFTENING_STEP
+            } else {
+                SOFTENING_STEP
+            };
+            self.simulation.softening_length = (self.simulation.softening_length + step).max(0.0);
+            self.simulation.diagnostics.reset();
+            info!("Softening length: {:.2}", self.simulation.softening_length);
+        }
+        // Toggle close-encounter sub-stepping on Digit9
+        if inputs.is_pressed(KeyCode::Digit9) {
+            self.simulation.substep_encounters = !self.simulation.substep_encounters;
+            info!(
+                "Encounter sub-stepping: {}",
+                self.simulation.substep_encounters
+            );
+        }
+        // Toggle parallel physics on KeyX, Shift+X logs the divergence from the serial path
+        if inputs.is_pressed(KeyCode::KeyX) {
+            if shift_modifier != 0 {
+                info!(
+                    "Parallel force divergence: {:.3e}",
+                    self.simulation.parallel_force_divergence()
+                );
+            } else {
+                self.simulation.parallel = !self.simulation.parallel;
+                info!("Parallel: {}", self.simulation.parallel);
+            }
+        }
+        // Toggle deterministic mode on KeyZ, Shift+Z logs the current state hash
+        if inputs.is_pressed(KeyCode::KeyZ) {
+            if shift_modifier != 0 {
+                info!(
+                    "Step {}: state hash {:016x}",
+                    self.simulation.step,
+                    self.simulation.state_hash()
+                );
+            } else {
+                self.simulation.deterministic = !self.simulation.deterministic;
+                info!(
+                    "Deterministic: {}, seed: {}",
+                    self.simulation.deterministic, self.simulation.seed
+                );
+            }
+        }
+        // Cycle world boundary on KeyO
+        if inputs.is_pressed(KeyCode::KeyO) {
+            self.simulation.boundary = self.simulation.boundary.next();
+            self.simulation.diagnostics.reset();
+            info!(
+                "Boundary: {:?} {:?}",
+                self.simulation.boundary, self.simulation.bounds
+            );
+        }
+        // Cycle launch trajectory prediction length on KeyP
+        if inputs.is_pressed(KeyCode::KeyP) {
+            let current = PREDICTION_STEPS
+                .iter()
+                .position(|&steps| steps == self.state.prediction_steps)
+                .unwrap_or(0);
+            self.state.prediction_steps = PREDICTION_STEPS[(current + 1) % PREDICTION_STEPS.len()];
+            info!("Prediction steps: {}", self.state.prediction_steps);
+        }
+        // Cycle trail length on KeyT, Shift+T cycles the colour, KeyJ cycles the fade
+        if inputs.is_pressed(KeyCode::KeyT) {
+            let trails = &mut self.trails;
+            if shift_modifier != 0 {
+                let current = TRAIL_COLOURS
+                    .iter()
+                    .position(|&colour| colour == trails.colour)
+                    .unwrap_or(0);
+                trails.colour = TRAIL_COLOURS[(current + 1) % TRAIL_COLOURS.len()];
+                info!("Trail colour: {:?}", trails.colour);
+            } else {
+                let current = TRAIL_LENGTHS
+                    .iter()
+                    .position(|&length| length == trails.length)
+                    .unwrap_or(0);
+                trails.length = TRAIL_LENGTHS[(current + 1) % TRAIL_LENGTHS.len()];
+                info!("Trail length: {}", trails.length);
+            }
+        }
+        if inputs.is_pressed(KeyCode::KeyJ) {
+            let current = TRAIL_FADES
+                .iter()
+                .position(|&fade| fade == self.trails.fade)
+                .unwrap_or(0);
+            self.trails.fade = TRAIL_FADES[(current + 1) % TRAIL_FADES.len()];
+            info!("Trail fade: {:.2}", self.trails.fade);
+        }
+        // Select the particle under the cursor on right click, logging it in the inspector
+        if let Some(click) = inputs.right_click {
+            self.selected = self.particle_under(click);
+            self.log_inspector();
+        }
+        // With a selection: Enter logs the inspector, Delete removes the particle,
+        // PageUp/PageDown scale its mass (Shift: radius), Home/End scale its speed (Shift: turn it)
+        if let Some(id) = self.selected {
+            let direction = |up: KeyCode, down: KeyCode| {
+                inputs.is_pressed(up) as i32 as f64 - inputs.is_pressed(down) as i32 as f64
+            };
+            let page = direction(KeyCode::PageUp, KeyCode::PageDown);
+            let home = direction(KeyCode::Home, KeyCode::End);
+            if page != 0.0 || home != 0.0 {
+                self.simulation.edit_particle(id, |p| {
+                    if shift_modifier != 0 {
+                        p.radius *= INSPECTOR_SCALE_STEP.powf(page);
+                        p.vel = p.vel.rotate(home * INSPECTOR_ROTATE_STEP);
+                    } else {
+                        p.mass *= INSPECTOR_SCALE_STEP.powf(page);
+                        p.vel *= INSPECTOR_SCALE_STEP.powf(home);
+                    }
+                });
+                self.log_inspector();
+            } else if inputs.is_pressed(KeyCode::Enter) {
+                self.log_inspector();
+            } else if inputs.is_pressed(KeyCode::Delete) {
+                self.simulation.remove_particle(id);
+                self.selected = None;
+                info!("Deleted particle {id}");
+            }
+        }
+        // Cycle particle colouring on KeyQ, Shift+Q cycles the gradient
+        if inputs.is_pressed(KeyCode::KeyQ) {
+            if shift_modifier != 0 {
+                self.colour_map.gradient = self.colour_map.gradient.next();
+                info!("Colour gradient: {:?}", self.colour_map.gradient);
+            } else {
+                self.colour_map.colour_by = self.colour_map.colour_by.next();
+                // a range fixed for one attribute means nothing for the next.
+                self.colour_map.range = None;
+                info!("Colour by: {:?}", self.colour_map.colour_by);
+            }
+        }
+        // Toggle a fixed colour range on KeyV, frozen from the current auto range
+        if inputs.is_pressed(KeyCode::KeyV) {
+            self.colour_map.range = match self.colour_map.range {
+                Some(_) => None,
+                None => Some(
+                    self.colour_map
+                        .measure_range(self.simulation.get_particles()),
+                ),
+            };
+            info!("Colour range: {:?} (None == auto)", self.colour_map.range);
+        }
+        // Log Barnes-Hut force error against the exact solution on KeyE
+        if inputs.is_pressed(KeyCode::KeyE) {
+            let error = self.simulation.force_error();
+            info!(
+                "Barnes-Hut force error (theta {:.2}, relative to the RMS acceleration): mean {:.3e} | p99 {:.3e}",
+                self.simulation.theta, error.mean, error.p99
+            );
         }
 
         // Branchless Camera Movement
-        self.camera_vel.y -= CAMERA_SPEED * inputs.is_held(KeyCode::KeyW) as i32 as f64;
-        self.camera_vel.y += CAMERA_SPEED * inputs.is_held(KeyCode::KeyS) as i32 as f64;
-        self.camera_vel.x += CAMERA_SPEED * inputs.is_held(KeyCode::KeyD) as i32 as f64;
-        self.camera_vel.x -= CAMERA_SPEED * inputs.is_held(KeyCode::KeyA) as i32 as f64;
+        self.camera.vel.y -= CAMERA_SPEED * inputs.is_held(KeyCode::KeyW) as i32 as f64;
+        self.camera.vel.y += CAMERA_SPEED * inputs.is_held(KeyCode::KeyS) as i32 as f64;
+        self.camera.vel.x += CAMERA_SPEED * inputs.is_held(KeyCode::KeyD) as i32 as f64;
+        self.camera.vel.x -= CAMERA_SPEED * inputs.is_held(KeyCode::KeyA) as i32 as f64;
+
+        // Cycle camera follow on KeyF (selected or nearest particle, centre of mass, co-rotating pair),
+        // Shift+F follows the selected or nearest particle straight away
+        if inputs.is_pressed(KeyCode::KeyF) {
+            let nearest = self
+                .simulation
+                .nearest_particle(self.mouse_to_world(inputs.mouse_pos));
+            // the selection, when there is one, beats whatever happens to be near the cursor.
+            let nearest = self.selected.or(nearest);
+            let follow = if shift_modifier != 0 {
+                nearest.map_or(Follow::Free, Follow::Particle)
+            } else {
+                match self.camera.follow {
+                    Follow::Free => nearest.map_or(Follow::CentreOfMass, Follow::Particle),
+                    Follow::Particle(_) => Follow::CentreOfMass,
+                    // the nearest body, turning with whatever it is most likely orbiting.
+                    Follow::CentreOfMass => nearest
+                        .zip(nearest.and_then(|id| self.simulation.heaviest_particle_except(id)))
+                        .map_or(Follow::Free, |(a, b)| Follow::CoRotating(a, b)),
+                    Follow::CoRotating(..) => Follow::Free,
+                }
+            };
+            self.camera.set_follow(follow, self.sim_size);
+            info!("Camera follow: {:?}", self.camera.follow);
+        }
+
+        // Zoom about the cursor on the scroll wheel
+        if inputs.scroll != 0.0 {
+            let anchor = inputs.mouse_pos.scale(self.state.scale);
+            self.camera
+                .zoom_at(anchor, CAMERA_ZOOM_STEP.powf(inputs.scroll));
+            trace!("Zoom: {:.3}", self.camera.zoom);
+        }
 
         // Branchless Draw Size Change
         self.state.draw_size +=
@@ -245,6 +780,231 @@ impl GravitySim {
             inputs.is_pressed(KeyCode::ArrowDown) as i32 * (1 + (shift_modifier * 5));
         self.state.draw_size = self.state.draw_size.clamp(1, MAX_DRAW_SIZE);
 
+        // Cycle the motion new particles spawn with on KeyK, Shift+K pins or frees the selected particle
+        if inputs.is_pressed(KeyCode::KeyK) {
+            if shift_modifier != 0 {
+                if let Some(id) = self.selected {
+                    self.simulation.edit_particle(id, |p| {
+                        p.motion = match p.motion {
+                            Motion::Static => Motion::Dynamic,
+                            _ => Motion::Static,
+                        };
+                    });
+                    self.log_inspector();
+                }
+            } else {
+                self.state.spawn_motion = self.state.spawn_motion.next();
+                info!("Spawn motion: {:?}", self.state.spawn_motion);
+            }
+        }
+
+        // Cycle the charge new particles spawn with on KeyU, Shift+U cycles the selected particle's
+        if inputs.is_pressed(KeyCode::KeyU) {
+            // charge per unit mass, so it means the same thing for any size of particle.
+            let next_charge = |charge_per_mass: f64| {
+                let current = CHARGE_PER_MASS
+                    .iter()
+                    .position(|&charge| charge == charge_per_mass)
+                    .unwrap_or(0);
+                CHARGE_PER_MASS[(current + 1) % CHARGE_PER_MASS.len()]
+            };
+            if shift_modifier != 0 {
+                if let Some(id) = self.selected {
+                    self.simulation.edit_particle(id, |p| {
+                        p.charge = next_charge(p.charge / p.mass) * p.mass;
+                    });
+                    self.log_inspector();
+                }
+            } else {
+                self.state.spawn_charge = next_charge(self.state.spawn_charge);
+                info!("Spawn charge per mass: {}", self.state.spawn_charge);
+            }
+        }
+        // Toggle electrostatics on Slash, Shift+Slash toggles gravity
+        if inputs.is_pressed(KeyCode::Slash) {
+            let forces = &mut self.simulation.forces;
+            if shift_modifier != 0 {
+                forces.gravity = !forces.gravity;
+            } else {
+                forces.electrostatics = !forces.electrostatics;
+            }
+            // the potential energy changes meaning, so does its drift.
+            self.simulation.diagnostics.reset();
+            info!(
+                "Gravity: {} | electrostatics: {}",
+                self.simulation.forces.gravity, self.simulation.forces.electrostatics
+            );
+        }
+        // Cycle the uniform magnetic field on Backslash, Shift+Backslash cycles the electric field
+        if inputs.is_pressed(KeyCode::Backslash) {
+            let forces = &mut self.simulation.forces;
+            if shift_modifier != 0 {
+                let current = ELECTRIC_FIELDS
+                    .iter()
+                    .position(|&strength| strength == forces.electric_field.x)
+                    .unwrap_or(0);
+                forces.electric_field =
+                    vec2(ELECTRIC_FIELDS[(current + 1) % ELECTRIC_FIELDS.len()], 0.0);
+                self.simulation.diagnostics.reset();
+                info!(
+                    "Electric field: {:?}",
+                    self.simulation.forces.electric_field
+                );
+            } else {
+                let current = MAGNETIC_FIELDS
+                    .iter()
+                    .position(|&strength| strength == forces.magnetic_field)
+                    .unwrap_or(0);
+                forces.magnetic_field = MAGNETIC_FIELDS[(current + 1) % MAGNETIC_FIELDS.len()];
+                info!("Magnetic field: {}", forces.magnetic_field);
+            }
+        }
+
+        // Cycle the spawn brush on KeyY (particle, jelly, truss, rope), Shift+Y cycles the lattice size
+        if inputs.is_pressed(KeyCode::KeyY) {
+            if shift_modifier != 0 {
+                let current = LATTICE_SIZES
+                    .iter()
+                    .position(|&size| size == self.state.lattice_size)
+                    .unwrap_or(0);
+                self.state.lattice_size = LATTICE_SIZES[(current + 1) % LATTICE_SIZES.len()];
+                info!("Lattice size: {}", self.state.lattice_size);
+            } else {
+                self.state.brush = self.state.brush.next();
+                info!("Brush: {:?}", self.state.brush);
+            }
+        }
+
+        // Toggle force fields on F1..F4 (gravity, attractor, vortex, drag), Shift+F1..F3 reverses
+        // the field, Shift+F4 switches drag between linear & quadratic
+        const FIELD_KEYS: [KeyCode; 4] = [KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4];
+        for (key, field) in FIELD_KEYS
+            .into_iter()
+            .zip(&mut self.simulation.force_fields)
+        {
+            if !inputs.is_pressed(key) {
+                continue;
+            }
+            if shift_modifier == 0 {
+                field.enabled = !field.enabled;
+            } else if let FieldKind::Drag { quadratic } = &mut field.kind {
+                *quadratic = !*quadratic;
+            } else {
+                field.strength = -field.strength;
+            }
+            info!("Force field: {field:?}");
+            self.simulation.diagnostics.reset();
+        }
+        // Cycle point field falloff on F5, toggle them following the cursor on F6
+        if inputs.is_pressed(KeyCode::F5) || inputs.is_pressed(KeyCode::F6) {
+            for field in &mut self.simulation.force_fields {
+                if field.centre().is_none() {
+                    continue;
+                }
+                if inputs.is_pressed(KeyCode::F5) {
+                    field.falloff = field.falloff.next();
+                } else {
+                    field.follows_cursor = !field.follows_cursor;
+                }
+                info!("Force field: {field:?}");
+            }
+            self.simulation.diagnostics.reset();
+        }
+        // Weaken or strengthen every enabled force field on F7/F8
+        let field_scale = inputs.is_pressed(KeyCode::F8) as i32 as f64
+            - inputs.is_pressed(KeyCode::F7) as i32 as f64;
+        if field_scale != 0.0 {
+            for field in self
+                .simulation
+                .force_fields
+                .iter_mut()
+                .filter(|field| field.enabled)
+            {
+                field.strength *= FIELD_STRENGTH_STEP.powf(field_scale);
+                info!("Force field: {field:?}");
+            }
+            self.simulation.diagnostics.reset();
+        }
+        // pinned fields stay wherever the cursor last left them.
+        let cursor = self.mouse_to_world(inputs.mouse_pos);
+        for field in &mut self.simulation.force_fields {
+            // a disabled field staying put keeps the stored accelerations valid.
+            if field.enabled && field.follows_cursor {
+                field.set_centre(cursor);
+            }
+        }
+
+        // Place an emitter aimed at the view centre on KeyN, or toggle the one under the cursor,
+        // Shift+N removes the emitter under the cursor. Placed emitters outlive resets & clears
+        if inputs.is_pressed(KeyCode::KeyN) {
+            let cursor = self.mouse_to_world(inputs.mouse_pos);
+            let near = self
+                .simulation
+                .emitter_near(cursor, SELECT_RADIUS_PX / self.camera.zoom);
+            match (near, shift_modifier != 0) {
+                (Some(i), true) => {
+                    let emitter = self.simulation.emitters.remove(i);
+                    info!("Removed emitter at {:?}", emitter.pos);
+                }
+                (Some(i), false) => {
+                    let emitter = &mut self.simulation.emitters[i];
+                    emitter.enabled = !emitter.enabled;
+                    info!("Emitter at {:?} enabled: {}", emitter.pos, emitter.enabled);
+                }
+                (None, true) => {}
+                (None, false) => {
+                    let aim = self.view_centre() - cursor;
+                    let direction = if aim.length() > 0.0 {
+                        aim.y.atan2(aim.x)
+                    } else {
+                        0.0
+                    };
+                    let seed = self.simulation.seed.wrapping_add(self.simulation.next_id);
+                    let mut emitter = Emitter::new(cursor, direction, seed);
+                    emitter.placed = true;
+                    self.simulation.emitters.push(emitter);
+                    info!("Emitter: {:?}", self.simulation.emitters.last());
+                }
+            }
+        }
+
+        // Scale the rate of the emitter under the cursor on F11/F12 (Shift: its speed),
+        // narrow or widen its cone on F9/F10 (Shift: scale its particle radius)
+        let emitter_edit = |down: KeyCode, up: KeyCode| {
+            inputs.is_pressed(up) as i32 as f64 - inputs.is_pressed(down) as i32 as f64
+        };
+        let scale = emitter_edit(KeyCode::F11, KeyCode::F12);
+        let widen = emitter_edit(KeyCode::F9, KeyCode::F10);
+        if scale != 0.0 || widen != 0.0 {
+            let cursor = self.mouse_to_world(inputs.mouse_pos);
+            if let Some(i) = self
+                .simulation
+                .emitter_near(cursor, SELECT_RADIUS_PX / self.camera.zoom)
+            {
+                let emitter = &mut self.simulation.emitters[i];
+                if shift_modifier != 0 {
+                    emitter.edit(0.0, scale, widen, 0.0);
+                } else {
+                    emitter.edit(scale, 0.0, 0.0, widen);
+                }
+                info!("Emitter: {emitter:?}");
+            }
+        }
+        // Save the placed emitters on Digit0, Shift+Digit0 loads them back over the current ones
+        if inputs.is_pressed(KeyCode::Digit0) {
+            if shift_modifier != 0 {
+                match self.simulation.load_emitters(EMITTER_SAVE_PATH) {
+                    Ok(count) => info!("Loaded {count} emitter(s) from {EMITTER_SAVE_PATH}"),
+                    Err(err) => warn!("Loading emitters from {EMITTER_SAVE_PATH}: {err}"),
+                }
+            } else {
+                match self.simulation.save_emitters(EMITTER_SAVE_PATH) {
+                    Ok(count) => info!("Saved {count} emitter(s) to {EMITTER_SAVE_PATH}"),
+                    Err(err) => warn!("Saving emitters to {EMITTER_SAVE_PATH}: {err}"),
+                }
+            }
+        }
+
         // Cycle shape on Tab
         if inputs.is_pressed(KeyCode::Tab) {
             unsafe {
@@ -253,17 +1013,24 @@ impl GravitySim {
             }
         }
 
-        // velocity is bounded by equilibrium point with resistance
-        // TODO(TOM): Change CAMERA_RESISTANCE to an easing function?
-        self.camera_vel *= CAMERA_RESISTANCE;
-        self.camera += self.camera_vel;
+        self.camera.update();
         self.state.mouse = inputs.mouse_pos;
     }
 
     fn handle_input_renders(&mut self, inputs: &mut InputData) {
         optick::event!("Handling Input Renders");
 
-        if inputs.is_mouse_dragging() {
+        if !inputs.is_mouse_dragging() {
+            self.prediction = None;
+        }
+        if inputs.is_mouse_dragging() && self.grab.is_none() {
+            // scripted spawns go where they're told, nothing to predict.
+            if self.state.prediction_steps > 0
+                && self.state.spawn_motion == SpawnMotion::Dynamic
+                && self.state.brush == Brush::Particle
+            {
+                self.render_prediction(inputs);
+            }
             Shape::draw_arrow(
                 inputs.mouse_pressed.pos.scale(self.state.scale).cast(),
                 inputs.mouse_pos.scale(self.state.scale).cast(),
@@ -280,36 +1047,193 @@ impl GravitySim {
     // endregion
 
     // region: Rendering
+    fn render_selection(&mut self, alpha: f64) {
+        let Some(id) = self.selected else {
+            return;
+        };
+        let Some(i) = self.simulation.index_of(id) else {
+            // merged, culled or cleared since it was selected.
+            self.selected = None;
+            return;
+        };
+
+        let p = self.simulation.particles.get(i);
+        let centre = self.camera.to_pixel(lerp(p.prev_pos, p.pos, alpha));
+        let radius = (p.radius * self.camera.zoom) as i32 + SELECT_MARGIN_PX;
+        let sim_size = self.sim_size;
+        Shape::draw_circle_clipped(centre, radius, false, sim_size, |x, y| {
+            self.write_to_buf(vec2(x, y), YELLOW);
+        });
+    }
+
+    fn log_diagnostics_history(&self) {
+        let diagnostics = self.simulation.get_diagnostics();
+        let (Some(initial), Some(first), Some(latest), Some(drift)) = (
+            diagnostics.initial(),
+            diagnostics.history().next(),
+            diagnostics.latest(),
+            diagnostics.drift(),
+        ) else {
+            info!("Diagnostics history: empty");
+            return;
+        };
+
+        let energies = diagnostics.history().map(|c| c.total_energy());
+        let (min, max) = energies.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), e| {
+            (min.min(e), max.max(e))
+        });
+        info!(
+            "Diagnostics history: {} samples, steps {}..={} | E initial {:.4e}, min {:.4e}, max {:.4e} | drift E {} | P {:.2e} | L {:.2e} | CoM {:.2}",
+            diagnostics.history().count(),
+            first.step,
+            latest.step,
+            initial.total_energy(),
+            min,
+            max,
+            drift.energy_label(),
+            drift.momentum,
+            drift.angular_momentum,
+            drift.centre_of_mass,
+        );
+    }
+
+    fn log_inspector(&self) {
+        let Some(inspection) = self.selected.and_then(|id| self.simulation.inspect(id)) else {
+            info!("Selected: none");
+            return;
+        };
+
+        let p = inspection.particle;
+        let units = self.simulation.units;
+        info!(
+            "Particle {}: {:?} | pos {:?} | vel {:?} | acc {:?} | force {:?} | mass {:.4e} ({:.4e} kg) | charge {:.4e} | radius {:.3} | speed {:.4e} ({:.4e} m/s)",
+            p.id,
+            p.motion,
+            p.pos,
+            p.vel,
+            p.acc,
+            p.force,
+            p.mass,
+            units.mass_to_si(p.mass),
+            p.charge,
+            p.radius,
+            inspection.speed,
+            units.speed_to_si(inspection.speed),
+        );
+        match inspection.orbit {
+            Some(orbit) => info!(
+                "  orbiting {}: distance {:.3} | relative speed {:.4e} | specific energy {:.4e} | {}",
+                orbit.primary,
+                orbit.distance,
+                orbit.relative_speed,
+                orbit.specific_energy,
+                orbit
+                    .semi_major_axis
+                    .map_or("unbound".to_string(), |a| format!("bound, a {a:.3}")),
+            ),
+            None => info!("  nothing else to orbit"),
+        }
+    }
+
+    fn render_bounds(&mut self) {
+        let (min, max) = (self.simulation.bounds.min, self.simulation.bounds.max());
+        // corners transformed one by one, a co-rotating camera turns the rectangle.
+        let corners = [min, vec2(max.x, min.y), max, vec2(min.x, max.y)]
+            .map(|corner| self.camera.to_pixel(corner));
+
+        for i in 0..corners.len() {
+            let (start, end) = (corners[i], corners[(i + 1) % corners.len()]);
+            Shape::draw_line(start, end, &mut |x, y| self.plot_clipped(vec2(x, y), GRAY));
+        }
+    }
+
+    fn render_prediction(&mut self, inputs: &InputData) {
+        optick::event!("Rendering Prediction");
+
+        let pressed = inputs.mouse_pressed.pos;
+        let pos = self.mouse_to_world(pressed);
+        let velocity = self.launch_velocity(pressed, inputs.mouse_pos);
+        let radius = self.spawn_radius();
+        let prediction = self.simulation.predict_trajectory(
+            &mut self.prediction,
+            pos,
+            velocity,
+            radius,
+            self.state.spawn_charge,
+            self.state.prediction_steps,
+        );
+
+        let camera = self.camera;
+        let to_render = |pos| camera.to_pixel(pos);
+
+        // dotted, the dash counter carries across segments so short steps still get gaps.
+        let mut pixel = 0;
+        for segment in prediction.path.windows(2) {
+            if self.simulation.draws_across_wrap(segment[0], segment[1]) {
+                continue;
+            }
+            Shape::draw_line(to_render(segment[0]), to_render(segment[1]), &mut |x, y| {
+                if (pixel / PREDICTION_DASH).is_multiple_of(2) {
+                    self.plot_clipped(vec2(x, y), GREEN);
+                }
+                pixel += 1;
+            });
+        }
+
+        let (marker, colour) = match prediction.end {
+            PredictionEnd::Running => return,
+            PredictionEnd::Collision(pos) => (pos, RED),
+            PredictionEnd::Escape(pos) => (pos, YELLOW),
+        };
+        let marker = to_render(marker);
+        Shape::CircleOutline.draw(self.state.draw_size.max(2), |off_x, off_y| {
+            self.plot_clipped(vec2(marker.x + off_x, marker.y + off_y), colour)
+        });
+    }
+
     fn render_particles(
         texture_buf: &[SyncCell<u8>],
-        particles: &[SyncCell<Particle>],
+        particles: &Particles,
+        colours: &[Rgba], // one per particle.
         sim_size: Vec2<i32, RenderSpace>,
-        camera: Vec2<f64, WorldSpace>,
+        camera: Camera,
+        alpha: f64,
     ) {
         optick::event!("Update Texture Buffer");
 
-        particles
-            .iter()
-            .map(|p| p.get_mut())
-            .map(|p| (p.pos.sub(camera), p.radius))
-            .filter(|(pos, radius)| {
+        let ParticleSlices {
+            pos,
+            prev_pos,
+            radius,
+            ..
+        } = particles.slices();
+        pos.iter()
+            .zip(prev_pos)
+            .zip(radius)
+            .zip(colours)
+            .map(|(((&pos, &prev_pos), &radius), &colour)| {
+                let pos = camera.to_render(lerp(prev_pos, pos, alpha));
+                (pos, radius * camera.zoom, colour)
+            })
+            .filter(|(pos, radius, _)| {
                 !(pos.x + radius < 0.0
                     || pos.y + radius < 0.0
                     || pos.x - radius >= f64::from(sim_size.x)
                     || pos.y - radius >= f64::from(sim_size.y))
             })
-            .for_each(|(pos, radius)| {
-                Shape::CircleFill.draw(radius as i32, |off_x, off_y| {
-                    let offset = pos.map(|n| n as i32) + vec2(off_x, off_y);
-                    if !(offset.x < 0
-                        || offset.y < 0
-                        || offset.x >= sim_size.x
-                        || offset.y >= sim_size.y)
-                    {
-                        let index = 4 * (offset.y * sim_size.x + offset.x) as usize;
-                        Self::write_colour(index, texture_buf, WHITE);
-                    }
-                });
+            .for_each(|(pos, radius, colour)| {
+                // sub-pixel bodies (e.g. true scale planets) still get a dot.
+                let centre = pos.map(|n| n as i32);
+                Shape::draw_circle_clipped(
+                    centre,
+                    (radius as i32).max(1),
+                    true,
+                    sim_size,
+                    |x, y| {
+                        let index = 4 * (y * sim_size.x + x) as usize;
+                        Self::write_colour(index, texture_buf, colour);
+                    },
+                );
             });
     }
 
@@ -368,7 +1292,10 @@ impl GravitySim {
             buf_clone.push(SyncCell::new(44));
         }
 
-        let simulation = Simulation::new();
+        // the world starts centred on the initial view.
+        let mut simulation = Simulation::new();
+        simulation.bounds =
+            Bounds::centred(sim_size.cast().cast_unit() / 2.0, simulation.bounds.size);
 
         let state = FrontendState {
             frame: 0,
@@ -378,6 +1305,11 @@ impl GravitySim {
             running: false,
             step_sim: false,
             mouse: vec2(0.0, 0.0),
+            prediction_steps: PREDICTION_STEPS[2],
+            spawn_motion: SpawnMotion::Dynamic,
+            spawn_charge: CHARGE_PER_MASS[0],
+            brush: Brush::Particle,
+            lattice_size: LATTICE_SIZES[0],
         };
 
         Self {
@@ -386,11 +1318,15 @@ impl GravitySim {
 
             window_size,
             sim_size,
-            camera: vec2(0.0, 0.0),
-            camera_vel: vec2(0.0, 0.0),
+            camera: Camera::new(),
             bufs: [buf, buf_clone],
             front_buffer: 0,
             simulation,
+            selected: None,
+            grab: None,
+            prediction: None,
+            trails: Trails::new(),
+            colour_map: ColourMap::new(),
         }
     }
 }
@@ -398,93 +1334,422 @@ impl GravitySim {
 impl Simulation {
     fn new() -> Self {
         Self {
-            particles: Vec::new(),
-            // particles: Vec::from(Self::init_particles()),
+            particles: Particles::new(),
+            next_id: 0,
+            solver: ForceSolver::BruteForce,
+            forces: ForceModel::new(),
+            force_fields: ForceField::defaults(),
+            emitters: Vec::new(),
+            constraints: Constraints::new(),
+            acc_key: None,
+            theta: BARNES_HUT_THETA,
+            integrator: Integrator::VelocityVerlet,
+            collision_mode: CollisionMode::Bounce,
+            restitution: COLLISION_RESTITUTION,
+            friction: COLLISION_FRICTION,
+            broad_phase: BroadPhase::SpatialHash,
+            step: 0,
+            diagnostics: Diagnostics::new(),
+            units: DEFAULT_UNITS,
+            softening: Softening::Plummer,
+            softening_length: GRAVITY_SOFTENING,
+            substep_encounters: false,
+            quarantined: Vec::new(),
+            parallel: true,
+            deterministic: false,
+            boundary: Boundary::Open,
+            bounds: Bounds::centred(vec2(0.0, 0.0), vec2(WORLD_WIDTH, WORLD_HEIGHT)),
+            escape_radius: ESCAPE_RADIUS,
+            scenario: Scenario::TwoBodies,
+            seed: INIT_SEED,
         }
     }
 
     fn update(&mut self, delta_time: f64) {
         optick::event!("Physics Update");
 
-        for (i, p1) in self.particles.iter().enumerate() {
-            let p1 = p1.get_mut();
+        self.advance(delta_time * SIM_TIME_SCALE);
+        if self.diagnostics.enabled {
+            self.update_diagnostics();
+        }
+    }
+
+    // One step of dt world time units, shared with the prediction background so it can't drift
+    // from the real run.
+    fn advance(&mut self, dt: f64) {
+        self.run_emitters(dt);
+        let ParticleSlicesMut { pos, prev_pos, .. } = self.particles.slices_mut();
+        prev_pos.copy_from_slice(pos);
+
+        self.integrate(dt);
+        self.apply_constraints();
+        self.resolve_collisions();
+        self.apply_walls();
+
+        // TODO(TOM): ideally cull particles in the same loop, mutability & iterator validity issues.
+        self.particles.retain(|p| p.mass != 0.0 && p.radius != 0.0);
+        self.cull_escaped();
+        self.quarantine_non_finite();
+
+        self.step += 1;
+    }
+
+    fn update_diagnostics(&mut self) {
+        let measured = Conservation::measure(self);
+        self.diagnostics.record(measured);
 
+        if !self.step.is_multiple_of(DIAGNOSTICS_LOG_INTERVAL) {
+            return;
+        }
+        if let (Some(latest), Some(drift)) = (self.diagnostics.latest(), self.diagnostics.drift()) {
+            info!(
+                "Step {} | E {:.4e} (KE {:.4e}, PE {:.4e}) | drift E {} | P {:.2e} | L {:.2e} | CoM {:.2}",
+                self.step,
+                latest.total_energy(),
+                latest.kinetic_energy,
+                latest.potential_energy,
+                drift.energy_label(),
+                drift.momentum,
+                drift.angular_momentum,
+                drift.centre_of_mass,
+            );
+        }
+    }
+
+    fn quarantine_non_finite(&mut self) {
+        let is_finite = |p: &Particle| {
+            p.pos.x.is_finite()
+                && p.pos.y.is_finite()
+                && p.vel.x.is_finite()
+                && p.vel.y.is_finite()
+                && p.mass.is_finite()
+        };
+        if self.particles.rows().all(|p| is_finite(&p)) {
+            return;
+        }
+
+        let (finite, non_finite): (Vec<_>, Vec<_>) = self.particles.rows().partition(is_finite);
+        for p in non_finite {
+            warn!(
+                "Step {}: quarantined non-finite particle, pos {:?} vel {:?} mass {:?}",
+                self.step, p.pos, p.vel, p.mass
+            );
+            self.quarantined.push(p);
+        }
+        let excess = self.quarantined.len().saturating_sub(QUARANTINE_CAPACITY);
+        self.quarantined.drain(..excess);
+        self.particles = finite.into_iter().collect();
+        self.diagnostics.reset();
+    }
+
+    fn gravity(&self) -> Gravity {
+        Gravity {
+            grav_const: self.units.grav_const(),
+            softening: self.softening,
+            length: self.softening_length,
+            period: (self.boundary == Boundary::Periodic).then_some(self.bounds.size),
+        }
+    }
+
+    // Deterministic mode never touches the thread pool, whatever the parallel toggle says.
+    fn use_parallel(&self) -> bool {
+        self.parallel && !self.deterministic
+    }
+
+    // Every column of every particle plus the step count, bit for bit.
+    fn state_hash(&self) -> u64 {
+        let mut hasher = StateHasher::new();
+        hasher.write_usize(self.step);
+        hasher.write_usize(self.particles.len());
+        for &id in self.particles.id() {
+            hasher.write_u64(id);
+        }
+        let columns = [
+            self.particles.pos(),
+            self.particles.prev_pos(),
+            self.particles.vel(),
+            self.particles.acc(),
+            self.particles.force(),
+        ];
+        for &v in columns.into_iter().flatten() {
+            hasher.write_vec2(v);
+        }
+        for &n in self
+            .particles
+            .mass()
+            .iter()
+            .chain(self.particles.radius())
+            .chain(self.particles.charge())
+        {
+            hasher.write_f64(n);
+        }
+        for &n in self.particles.collisions() {
+            hasher.write_u32(n);
+        }
+        for &motion in self.particles.motion() {
+            motion.hash(&mut hasher);
+        }
+        self.constraints.hash(&mut hasher);
+        hasher.write_usize(self.emitters.len());
+        for emitter in &self.emitters {
+            emitter.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    fn get_diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    // Gravity only, contacts are resolved once per step after integration (resolve_collisions).
+    fn update_forces(&mut self) {
+        optick::event!("Physics Update - Forces");
+
+        self.particles.force_mut().fill(vec2(0.0, 0.0));
+
+        if self.forces.gravity {
+            match (self.solver, self.use_parallel()) {
+                (ForceSolver::BruteForce, false) => self.update_forces_brute_force(),
+                (ForceSolver::BruteForce, true) => self.update_forces_brute_force_parallel(),
+                (ForceSolver::BarnesHut, _) => self.update_forces_barnes_hut(),
+            }
+        }
+        if self.forces.electrostatics {
+            self.update_forces_electrostatic();
+        }
+        self.update_forces_fields();
+        self.update_forces_springs();
+        self.update_forces_external();
+    }
+
+    fn update_forces_brute_force(&mut self) {
+        let gravity = self.gravity();
+        let ParticleSlicesMut {
+            pos,
+            force,
+            mass,
+            radius,
+            ..
+        } = self.particles.slices_mut();
+
+        for i in 0..pos.len() {
             // calculates forces from other particles on this particle.
-            for (j, p2) in self.particles.iter().enumerate().skip(i) {
-                let p2 = p2.get_mut();
-                if i == j {
-                    // println!("equal! {i}, {j}");
-                    continue;
-                }
+            for j in i + 1..pos.len() {
+                // Applying gravity between the particles.
+                let pull = gravity_between(
+                    pos[i],
+                    mass[i],
+                    pos[j],
+                    mass[j],
+                    radius[i] + radius[j],
+                    gravity,
+                );
+                force[i] += pull;
+                force[j] -= pull;
+            }
+        }
+    }
 
-                p1.apply_physics(p2);
+    // Each particle sums its own pulls in index order, the same order the pairwise loop adds
+    // them in, and pair forces are exactly antisymmetric, so the result is bit-identical.
+    fn update_forces_brute_force_parallel(&mut self) {
+        let gravity = self.gravity();
+        let ParticleSlicesMut {
+            pos,
+            force,
+            mass,
+            radius,
+            ..
+        } = self.particles.slices_mut();
+        let (pos, mass, radius): (&[_], &[_], &[_]) = (pos, mass, radius);
+
+        for_each_mut(true, force, |i, force| {
+            for j in (0..pos.len()).filter(|&j| j != i) {
+                *force += gravity_between(
+                    pos[i],
+                    mass[i],
+                    pos[j],
+                    mass[j],
+                    radius[i] + radius[j],
+                    gravity,
+                );
             }
+        });
+    }
 
-            // Inner loop skips i, therefore skips this particel from now on,
-            // so apply resitances & update position.
-            p1.acc += (p1.force / p1.mass) * delta_time;
-            p1.acc *= PHYSICS_RESISTANCE;
-            p1.vel += p1.acc;
-            p1.pos += p1.vel;
+    // Tree walks are independent per particle, parallel only changes which thread runs them.
+    fn update_forces_barnes_hut(&mut self) {
+        let tree = QuadTree::new(&self.particles);
+        let gravity = self.gravity();
+        let particles = &self.particles;
+        let force_on = |i| tree.force_on(i, particles, self.theta, gravity);
+        let forces: Vec<_> = if self.use_parallel() {
+            (0..particles.len()).into_par_iter().map(force_on).collect()
+        } else {
+            (0..particles.len()).map(force_on).collect()
+        };
 
-            p1.force = vec2(0.0, 0.0);
-            // println!("{p1:#?}");
+        for_each_mut(
+            self.use_parallel(),
+            self.particles.force_mut(),
+            |i, force| {
+                *force += forces[i];
+            },
+        );
+    }
+
+    // Largest difference between the serial & parallel force passes, expected to be exactly 0.
+    fn parallel_force_divergence(&mut self) -> f64 {
+        let (parallel, deterministic) = (self.parallel, self.deterministic);
+        // force is part of the state hash, just measuring mustn't change it.
+        let saved = self.particles.force().to_vec();
+        self.deterministic = false;
+        let mut forces = [Vec::new(), Vec::new()];
+        for (forces, parallel) in forces.iter_mut().zip([false, true]) {
+            self.parallel = parallel;
+            self.update_forces();
+            *forces = self.particles.force().to_vec();
         }
+        (self.parallel, self.deterministic) = (parallel, deterministic);
+        self.particles.force_mut().copy_from_slice(&saved);
 
-        // TODO(TOM): ideally cull particles in the same loop, mutability & iterator validity issues.
-        self.particles
-            .retain(|p| p.get().mass != 0.0 && p.get().radius != 0.0);
-    }
-
-    /*
-    fn update_cursor_attract(&mut self, mouse: Vec2<f64, ScreenSpace>) {
-        optick::event!("Physics Update - Cursor");
-        let mouse = mouse.cast_unit();
-
-        // All particles attract to mouse.
-        self.particles
-            .par_iter_mut()
-            .map(|p| p.get_mut())
-            .for_each(|p| {
-                let dist = p.pos - mouse;
-                let abs_dist = f64::sqrt(dist.x.pow(2) + dist.y.pow(2));
-
-                if abs_dist > 5.0 {
-                    // If collapsing in on cursor, give it some velocity.
-                    let normal = p.pos.sub(mouse).mul(1.0 / abs_dist * PHYSICS_MULTIPLIER);
-                    p.vel -= normal;
-                } else {
-                    // Branchless!
-                    let mut delta = vec2(-1.0, -1.0);
-                    let are_vels_neg = p.vel.map(|n| (n < 0.0) as i32 as f64);
-                    delta += are_vels_neg * 2.0;
-                    p.vel += delta;
+        forces[0]
+            .iter()
+            .zip(&forces[1])
+            .map(|(&serial, &parallel)| (serial - parallel).length())
+            .fold(0.0, f64::max)
+    }
+
+    fn resolve_collisions(&mut self) {
+        optick::event!("Physics Update - Collisions");
+
+        let gravity = self.gravity();
+        let wrap = (self.boundary == Boundary::Periodic).then_some(self.bounds);
+        // contacts are sparse, so each pair is copied out, resolved and written back.
+        for (i, j) in self.broad_phase.candidate_pairs(&self.particles, wrap) {
+            let mut p1 = self.particles.get(i);
+            let mut p2 = self.particles.get(j);
+            // already absorbed by a merge this step.
+            if p1.mass == 0.0 || p2.mass == 0.0 {
+                continue;
+            }
+
+            let dist = gravity.separation(p1.pos, p2.pos);
+            let abs_dist = dist.length();
+            if abs_dist < p1.radius + p2.radius {
+                // touching across a seam, resolve against the nearest image, apply_walls wraps
+                // it back afterwards.
+                if wrap.is_some() {
+                    let image = p1.pos + dist - p2.pos;
+                    p2.pos += image;
+                    p2.prev_pos += image;
                 }
-                p.vel *= PHYSICS_RESISTANCE;
-                p.pos += p.vel;
-            });
+                // coincident bodies have no direction between them, push them apart along x.
+                let normal = if abs_dist > 0.0 {
+                    dist / abs_dist
+                } else {
+                    vec2(1.0, 0.0)
+                };
+                p1.handle_collision(
+                    &mut p2,
+                    abs_dist,
+                    normal,
+                    self.collision_mode,
+                    self.restitution,
+                    self.friction,
+                );
+                p1.collisions += 1;
+                p2.collisions += 1;
+                self.particles.set(i, p1);
+                self.particles.set(j, p2);
+            }
+        }
+    }
+
+    fn force_error(&self) -> barnes_hut::ForceError {
+        QuadTree::force_error(&self.particles, self.theta, self.gravity())
     }
-    */
 
-    fn reset(&mut self) {
+    // Reloads the current scenario around centre, scenarios bring their own units.
+    fn reset(&mut self, centre: Vec2<f64, WorldSpace>) {
         self.clear();
-        self.particles.extend_from_slice(&Self::init_particles());
+        self.bounds = Bounds::centred(centre, self.bounds.size);
+        let scene = self.scenario.build(centre, self.seed);
+        self.particles = scene.particles;
+        for id in self.particles.id_mut() {
+            *id = self.next_id;
+            self.next_id += 1;
+        }
+        self.units = scene.units;
+        self.softening_length = scene.softening;
+        // scenarios come in their own units & sizes, open space culls relative to the scene.
+        let extent = self
+            .particles
+            .rows()
+            .map(|p| (p.pos - centre).length() + p.radius)
+            .fold(0.0, f64::max);
+        if extent > 0.0 {
+            self.escape_radius = extent * ESCAPE_RADIUS_SCALE;
+        }
+        // placed emitters survived the clear, the scenario's own go first.
+        self.emitters.splice(0..0, scene.emitters);
+        info!(
+            "Scenario: {:?}, seed: {}, {} particles, escape radius {:.1}",
+            self.scenario,
+            self.seed,
+            self.particles.len(),
+            self.escape_radius
+        );
     }
 
     fn clear(&mut self) {
         self.particles.clear();
+        self.constraints.links.clear();
+        self.emitters.retain(|emitter| emitter.placed);
+        self.emitters.iter_mut().for_each(Emitter::restart);
+        self.acc_key = None;
+        self.next_id = 0;
+        // scenarios bring their own units, an empty world goes back to the sandbox's.
+        self.units = DEFAULT_UNITS;
+        self.softening_length = GRAVITY_SOFTENING;
+        self.escape_radius = ESCAPE_RADIUS;
+        self.quarantined.clear();
+        self.diagnostics.reset();
     }
 
-    fn get_particles(&self) -> &[SyncCell<Particle>] {
-        self.particles.as_slice()
+    fn get_particles(&self) -> &Particles {
+        &self.particles
     }
 
-    fn init_particles() -> [SyncCell<Particle>; 2] {
-        const RADIUS: f64 = 60.0;
-        [
-            create_particle(vec2(120.0, 120.0), vec2(0.0, 0.0), RADIUS),
-            create_particle(vec2(320.0, 320.0), vec2(0.0, 0.0), RADIUS),
-        ]
+    // Id of the particle closest to pos, by distance to its surface.
+    fn nearest_particle(&self, pos: Vec2<f64, WorldSpace>) -> Option<u64> {
+        let ParticleSlices {
+            id,
+            pos: positions,
+            radius,
+            ..
+        } = self.particles.slices();
+        (0..id.len())
+            .min_by(|&a, &b| {
+                let surface = |i: usize| (positions[i] - pos).length() - radius[i];
+                surface(a).total_cmp(&surface(b))
+            })
+            .map(|i| id[i])
+    }
+
+    fn heaviest_particle_except(&self, except: u64) -> Option<u64> {
+        let ParticleSlices { id, mass, .. } = self.particles.slices();
+        (0..id.len())
+            .filter(|&i| id[i] != except)
+            .max_by(|&a, &b| mass[a].total_cmp(&mass[b]))
+            .map(|i| id[i])
+    }
+
+    fn add_particle(&mut self, mut particle: Particle) {
+        particle.id = self.next_id;
+        self.next_id += 1;
+        self.particles.push(particle);
+        self.acc_key = None;
     }
 
     fn spawn_particle(
@@ -492,47 +1757,89 @@ impl Simulation {
         pos: Vec2<f64, WorldSpace>,
         vel: Vec2<f64, WorldSpace>,
         radius: f64,
+        motion: Motion,
+        charge_per_mass: f64,
     ) {
-        self.particles.push(create_particle(pos, vel, radius));
+        let density = self.units.density_from_si(EARTH_DENSITY);
+        let particle = create_particle(pos, vel, radius, density);
+        self.add_particle(Particle {
+            motion,
+            charge: charge_per_mass * particle.mass,
+            ..particle
+        });
+        self.diagnostics.reset();
     }
 }
 
 impl Particle {
-    /*
+    // Momentum conserving accretion, self absorbs p2 at the combined centre of mass.
+    // Scripted bodies stay on their path instead.
     fn combine_particles(&mut self, p2: &mut Particle) {
-        let consumer_pos = if self.mass > p2.mass {
-            self.pos
-        } else {
-            p2.pos
-        };
         let new_mass = self.mass + p2.mass;
         let new_momentum: Vec2<f64, WorldSpace> = self.vel * self.mass + p2.vel * p2.mass;
-        let new_radius = f64::sqrt(self.radius.pow(2) + p2.radius.pow(2));
+        let (pos, vel) = if self.motion.is_dynamic() {
+            (
+                (self.pos * self.mass + p2.pos * p2.mass) / new_mass,
+                new_momentum / new_mass,
+            )
+        } else {
+            (self.pos, self.vel)
+        };
+        // same density, so volumes add.
+        let new_radius = f64::cbrt(self.radius.pow(3) + p2.radius.pow(3));
 
         *self = Particle {
-            pos: consumer_pos,
-            vel: new_momentum / new_mass,
+            id: self.id,
+            pos,
+            prev_pos: pos,
+            vel,
+            acc: (self.acc * self.mass + p2.acc * p2.mass) / new_mass,
             force: vec2(0.0, 0.0),
             mass: new_mass,
             radius: new_radius,
+            collisions: self.collisions + p2.collisions,
+            charge: self.charge + p2.charge,
+            motion: self.motion,
         };
 
-        // will be culled later.
-        *p2 = Particle {
-            pos: vec2(f64::MIN, f64::MIN), // TODO(TOM): MIN might cause slowdowns? prob not..
-            vel: vec2(0.0, 0.0),
-            force: vec2(0.0, 0.0),
-            mass: 0.0,
-            radius: 0.0,
-        };
+        // will be culled at the end of the step.
+        p2.mass = 0.0;
+        p2.radius = 0.0;
     }
-    */
+
     fn handle_collision(
         &mut self,
         p2: &mut Particle,
         abs_dist: f64,
         normal: Vec2<f64, WorldSpace>,
+        mode: CollisionMode,
+        restitution: f64,
+        friction: f64,
     ) {
+        if mode == CollisionMode::Merge {
+            // scripted bodies absorb anything dynamic, otherwise the larger body absorbs the smaller,
+            // so it keeps its identity (id).
+            let absorbs = match (self.motion.is_dynamic(), p2.motion.is_dynamic()) {
+                (false, true) => true,
+                (true, false) => false,
+                _ => self.mass >= p2.mass,
+            };
+            if absorbs {
+                self.combine_particles(p2);
+            } else {
+                p2.combine_particles(self);
+            }
+            return;
+        }
+
+        // scripted bodies have no inverse mass, so they're never pushed around.
+        let (inverse_mass1, inverse_mass2) = (self.inverse_mass(), p2.inverse_mass());
+        let normalised_combined_mass = inverse_mass1 + inverse_mass2;
+        // two scripted bodies just pass through each other.
+        if normalised_combined_mass == 0.0 {
+            return;
+        }
+
         let min_dist = self.radius + p2.radius;
 
         let overlap = min_dist - abs_dist;
@@ -541,74 +1848,523 @@ impl Particle {
         let velocity_delta = p2.vel - self.vel;
 
         // project relative velocity (velocity_delta) along normal vector
-        let velocity_along_normal = velocity_delta.x * normal.x + velocity_delta.y * normal.y;
+        let velocity_along_normal = velocity_delta.dot(normal);
 
-        let combined_mass = self.mass + p2.mass;
         let separation_factor = 1.1;
 
-        let p1_correction = (overlap * separation_factor) * (p2.mass / combined_mass);
-        let p2_correction = (overlap * separation_factor) * (self.mass / combined_mass);
+        let p1_correction =
+            (overlap * separation_factor) * (inverse_mass1 / normalised_combined_mass);
+        let p2_correction =
+            (overlap * separation_factor) * (inverse_mass2 / normalised_combined_mass);
 
         self.pos -= normal * p1_correction;
         p2.pos += normal * p2_correction;
 
-        // only rebound if they are moving towards each other. ?
-        if velocity_along_normal < 0.0 {
-            let normalised_combined_mass = 1.0 / self.mass + 1.0 / p2.mass;
-            // let impulse_scalar =
-            //     -COLLISION_RESTITUTION * velocity_along_normal / normalised_combined_mass;
-
-            let impulse_scalar = (self.mass + p2.mass) * 0.1 * velocity_along_normal.abs();
-            // let impulse_scalar = impulse_scalar.max(min_impulse_scalar);
-
-            // Apply rebound impulse to particles.
-            self.vel -= normal * (impulse_scalar / self.mass);
-            p2.vel += normal * (impulse_scalar / p2.mass);
+        // only rebound if they are moving towards each other.
+        if velocity_along_normal >= 0.0 {
+            return;
+        }
 
-            // add slight "random factor"
-            // let perpendicular_normal = vec2(-normal.y, normal.x);
-            // let random_factor = 0.05 * impulse_scalar;
-            // self.vel += (perpendicular_normal * random_factor) / self.mass;
-            // p2.vel -= (perpendicular_normal * random_factor) / p2.mass;
+        if mode == CollisionMode::Inelastic {
+            // perfectly inelastic, both leave with the shared centre of mass velocity.
+            let shared_vel =
+                (self.vel * inverse_mass2 + p2.vel * inverse_mass1) / normalised_combined_mass;
+            self.vel = shared_vel;
+            p2.vel = shared_vel;
+            return;
         }
-    }
 
-    fn apply_physics(&mut self, p2: &mut Particle) {
-        let dist = p2.pos - self.pos;
+        let impulse_scalar =
+            -(1.0 + restitution) * velocity_along_normal / normalised_combined_mass;
 
-        // this is the magnituce of distance between p1,p2
-        let abs_dist = f64::sqrt(dist.x.pow(2) + dist.y.pow(2));
-        let normal = dist / abs_dist;
+        // Apply rebound impulse to particles.
+        self.vel -= normal * (impulse_scalar * inverse_mass1);
+        p2.vel += normal * (impulse_scalar * inverse_mass2);
 
-        let min_distance = self.radius + p2.radius;
-        let collision_occurred = abs_dist < min_distance;
+        // Coulomb friction, tangential impulse capped at friction * normal impulse.
+        let tangent_vel = velocity_delta - normal * velocity_along_normal;
+        let tangent_speed = tangent_vel.length();
+        if friction > 0.0 && tangent_speed > SMALL_VALUE {
+            let tangent = tangent_vel / tangent_speed;
+            let friction_impulse =
+                (tangent_speed / normalised_combined_mass).min(friction * impulse_scalar);
 
-        if collision_occurred {
-            self.handle_collision(p2, abs_dist, normal);
-            return;
+            self.vel += tangent * (friction_impulse * inverse_mass1);
+            p2.vel -= tangent * (friction_impulse * inverse_mass2);
         }
+    }
+}
+
+// Pull of body 2 on body 1, exactly the negation of the pull of 1 on 2.
+fn gravity_between(
+    pos1: Vec2<f64, WorldSpace>,
+    mass1: f64,
+    pos2: Vec2<f64, WorldSpace>,
+    mass2: f64,
+    min_distance: f64,
+    gravity: Gravity,
+) -> Vec2<f64, WorldSpace> {
+    // this is the magnituce of distance between p1,p2
+    let abs_dist = gravity.separation(pos1, pos2).length();
+
+    // overlapping particles are pushed apart by resolve_collisions instead.
+    if abs_dist < min_distance {
+        return vec2(0.0, 0.0);
+    }
+
+    gravity_force(pos1, mass1, pos2, mass2, gravity)
+}
 
-        // Applying gravity between the particles.
-        let abs_force =
-            (GRAV_CONST * PHYSICS_MULTIPLIER * self.mass * p2.mass) / (abs_dist.pow(2.0) * 1.5);
-        let force = normal * abs_force;
+// Potential energy of a pair, flat inside contact distance where gravity is switched off.
+fn gravity_potential(
+    pos1: Vec2<f64, WorldSpace>,
+    mass1: f64,
+    pos2: Vec2<f64, WorldSpace>,
+    mass2: f64,
+    min_distance: f64,
+    gravity: Gravity,
+) -> f64 {
+    let abs_dist = gravity.separation(pos1, pos2).length().max(min_distance);
+    -gravity.grav_const * mass1 * mass2 * gravity.potential_kernel(abs_dist)
+}
 
-        self.force += force;
-        p2.force -= force;
+// Gravitational pull on a body at pos1 towards a body at pos2.
+fn gravity_force(
+    pos1: Vec2<f64, WorldSpace>,
+    mass1: f64,
+    pos2: Vec2<f64, WorldSpace>,
+    mass2: f64,
+    gravity: Gravity,
+) -> Vec2<f64, WorldSpace> {
+    let dist = gravity.separation(pos1, pos2);
+    let abs_dist = dist.length();
+    // coincident (or already non-finite) bodies have no direction, they must not spread NaN.
+    if !(abs_dist > 0.0 && abs_dist.is_finite()) {
+        return vec2(0.0, 0.0);
     }
+    let normal = dist / abs_dist;
+
+    // m1 * m2 first, so swapping the pair gives exactly the negated force.
+    let abs_force = gravity.grav_const * (mass1 * mass2) * gravity.force_kernel(abs_dist);
+    normal * abs_force
 }
 
 fn create_particle(
     pos: Vec2<f64, WorldSpace>,
     vel: Vec2<f64, WorldSpace>,
     radius: f64,
-) -> SyncCell<Particle> {
-    SyncCell::new(Particle {
+    density: f64,
+) -> Particle {
+    create_particle_with_mass(pos, vel, radius, sphere_mass(radius, density))
+}
+
+fn create_particle_with_mass(
+    pos: Vec2<f64, WorldSpace>,
+    vel: Vec2<f64, WorldSpace>,
+    radius: f64,
+    mass: f64,
+) -> Particle {
+    Particle {
+        id: 0,
         radius,
-        mass: f64::consts::PI * 4.0 / 3.0 * radius.pow(3) * EARTH_DENSITY,
+        mass,
         pos,
+        prev_pos: pos,
         vel,
         acc: vec2(0.0, 0.0),
         force: vec2(0.0, 0.0),
-    })
+        collisions: 0,
+        charge: 0.0,
+        motion: Motion::Dynamic,
+    }
+}
+
+fn lerp(
+    from: Vec2<f64, WorldSpace>,
+    to: Vec2<f64, WorldSpace>,
+    alpha: f64,
+) -> Vec2<f64, WorldSpace> {
+    from + (to - from) * alpha
+}
+
+fn sphere_mass(radius: f64, density: f64) -> f64 {
+    f64::consts::PI * 4.0 / 3.0 * radius.pow(3) * density
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seeded(scenario: Scenario, seed: u64) -> Simulation {
+        let mut sim = Simulation::new();
+        sim.scenario = scenario;
+        sim.seed = seed;
+        sim.reset(vec2(0.0, 0.0));
+        sim
+    }
+
+    #[test]
+    fn seeded_runs_replay_to_identical_hashes() {
+        let run = || {
+            let mut sim = seeded(Scenario::PlummerSphere, INIT_SEED);
+            sim.deterministic = true;
+            (0..20)
+                .map(|step| {
+                    // the same input at the same step, like a replayed click.
+                    if step == 10 {
+                        sim.spawn_particle(
+                            vec2(0.0, 0.0),
+                            vec2(0.1, 0.0),
+                            2.0,
+                            Motion::Dynamic,
+                            0.0,
+                        );
+                    }
+                    sim.update(SIM_TIMESTEP.as_secs_f64());
+                    sim.state_hash()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(), run());
+        assert_ne!(
+            seeded(Scenario::PlummerSphere, INIT_SEED).state_hash(),
+            seeded(Scenario::PlummerSphere, INIT_SEED + 1).state_hash()
+        );
+    }
+
+    #[test]
+    fn parallel_forces_match_serial() {
+        for solver in [ForceSolver::BruteForce, ForceSolver::BarnesHut] {
+            let mut sim = seeded(Scenario::PlummerSphere, INIT_SEED);
+            sim.solver = solver;
+            sim.update(SIM_TIMESTEP.as_secs_f64());
+            let hash = sim.state_hash();
+            assert_eq!(sim.parallel_force_divergence(), 0.0, "{solver:?}");
+            assert_eq!(sim.state_hash(), hash, "{solver:?}");
+        }
+    }
+
+    #[test]
+    fn barnes_hut_stays_close_to_the_exact_sum() {
+        let mut sim = seeded(Scenario::GalacticDisk, INIT_SEED);
+        let error_at = |sim: &mut Simulation, theta: f64| {
+            sim.theta = theta;
+            sim.force_error()
+        };
+
+        // never opening a node is the exact sum.
+        let exact = error_at(&mut sim, 0.0);
+        assert_eq!((exact.mean, exact.p99), (0.0, 0.0));
+        // a few tenths of a percent of the typical pull at the default opening angle.
+        let default = error_at(&mut sim, BARNES_HUT_THETA);
+        assert!(default.mean < 2e-3, "{default:?}");
+        assert!(default.p99 < 1e-2, "{default:?}");
+        // & a wider angle trades accuracy for speed.
+        let wide = error_at(&mut sim, 1.0);
+        assert!(wide.mean > default.mean, "{wide:?}");
+    }
+
+    #[test]
+    fn higher_order_integrators_drift_less() {
+        let worst_drift = |integrator| {
+            let mut sim = seeded(Scenario::FigureEight, INIT_SEED);
+            sim.integrator = integrator;
+            let energy = |sim: &Simulation| Conservation::measure(sim).total_energy();
+            let start = energy(&sim);
+            (0..3000)
+                .map(|_| {
+                    sim.update(SIM_TIMESTEP.as_secs_f64());
+                    ((energy(&sim) - start) / start).abs()
+                })
+                .fold(0.0, f64::max)
+        };
+        let euler = worst_drift(Integrator::ExplicitEuler);
+        let semi_implicit = worst_drift(Integrator::SemiImplicitEuler);
+        let leapfrog = worst_drift(Integrator::Leapfrog);
+        let verlet = worst_drift(Integrator::VelocityVerlet);
+        let rk4 = worst_drift(Integrator::Rk4);
+
+        assert!(semi_implicit < euler / 10.0);
+        assert!(leapfrog < semi_implicit / 10.0);
+        // kick-drift-kick & velocity Verlet are the same scheme written two ways.
+        assert!((verlet - leapfrog).abs() < leapfrog * 1e-3);
+        assert!(rk4 < leapfrog);
+    }
+
+    #[test]
+    fn collision_modes_conserve_momentum() {
+        // a heavy & a light body, overlapping head on.
+        let collide = |mode: CollisionMode| {
+            let mut sim = Simulation::new();
+            sim.clear();
+            sim.collision_mode = mode;
+            sim.restitution = 1.0;
+            sim.friction = 0.0;
+            sim.spawn_particle(vec2(0.0, 0.0), vec2(1.0, 0.0), 2.0, Motion::Dynamic, 0.0);
+            sim.spawn_particle(vec2(2.5, 0.5), vec2(-3.0, 0.0), 1.0, Motion::Dynamic, 0.0);
+            let before = sim.particles.clone();
+            sim.resolve_collisions();
+            (before, sim.particles)
+        };
+        let momentum = |particles: &Particles| {
+            particles
+                .rows()
+                .fold(vec2(0.0, 0.0), |sum, p| sum + p.vel * p.mass)
+        };
+        let kinetic = |particles: &Particles| {
+            particles
+                .rows()
+                .map(|p| 0.5 * p.mass * p.vel.dot(p.vel))
+                .sum::<f64>()
+        };
+
+        for mode in [
+            CollisionMode::Bounce,
+            CollisionMode::Inelastic,
+            CollisionMode::Merge,
+        ] {
+            let (before, after) = collide(mode);
+            let lost = (momentum(&after) - momentum(&before)).length();
+            assert!(lost < 1e-9 * momentum(&before).length(), "{mode:?}");
+            let (start, end) = (kinetic(&before), kinetic(&after));
+            match mode {
+                // elastic, they rebound with all of their kinetic energy.
+                CollisionMode::Bounce => {
+                    assert!((end - start).abs() < 1e-9 * start);
+                    assert!(after.vel()[0].x < before.vel()[0].x);
+                    assert!(after.vel()[1].x > 0.0);
+                }
+                CollisionMode::Inelastic => {
+                    assert_eq!(after.vel()[0], after.vel()[1]);
+                    assert!(end < start);
+                }
+                // the heavier body keeps its id & takes all of the mass.
+                CollisionMode::Merge => {
+                    assert_eq!(after.mass()[1], 0.0);
+                    assert_eq!(after.mass()[0], before.mass().iter().sum::<f64>());
+                    assert_eq!(after.id()[0], before.id()[0]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn broad_phases_find_the_same_pairs() {
+        // a scattered crowd reaching over every seam, with one body far wider than a hash cell.
+        let mut sim = Simulation::new();
+        sim.clear();
+        let (min, size) = (sim.bounds.min, sim.bounds.size);
+        for i in 0..400 {
+            let t = i as f64;
+            let pos = vec2(
+                min.x + (t * 0.618_034).fract() * size.x,
+                min.y + (t * 0.414_214).fract() * size.y,
+            );
+            let radius = 1.0 + (t * 0.3).sin().abs() * 20.0;
+            sim.spawn_particle(pos, vec2(0.0, 0.0), radius, Motion::Dynamic, 0.0);
+        }
+        sim.spawn_particle(min, vec2(0.0, 0.0), 200.0, Motion::Dynamic, 0.0);
+
+        for wrap in [None, Some(sim.bounds)] {
+            let reference = BroadPhase::AllPairs.candidate_pairs(&sim.particles, wrap);
+            assert!(!reference.is_empty());
+            for broad_phase in [BroadPhase::SpatialHash, BroadPhase::SweepAndPrune] {
+                let pairs = broad_phase.candidate_pairs(&sim.particles, wrap);
+                assert_eq!(
+                    pairs,
+                    reference,
+                    "{broad_phase:?}, wrapping: {}",
+                    wrap.is_some()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn only_open_space_culls_escaped_particles() {
+        for boundary in [Boundary::Open, Boundary::Reflective, Boundary::Periodic] {
+            let mut sim = Simulation::new();
+            sim.clear();
+            sim.boundary = boundary;
+            let centre = sim.bounds.centre();
+            let outside = centre + vec2(sim.escape_radius * 1.5, 0.0);
+            sim.spawn_particle(centre, vec2(0.0, 0.0), 1.0, Motion::Dynamic, 0.0);
+            sim.spawn_particle(outside, vec2(0.0, 0.0), 1.0, Motion::Dynamic, 0.0);
+            sim.update(SIM_TIMESTEP.as_secs_f64());
+
+            if boundary == Boundary::Open {
+                assert_eq!(sim.particles.id(), [0]);
+            } else {
+                // walled or wrapped back into the world instead.
+                assert_eq!(sim.particles.len(), 2, "{boundary:?}");
+                let (min, max) = (sim.bounds.min, sim.bounds.max());
+                assert!(
+                    sim.particles.pos().iter().all(|pos| {
+                        (min.x..=max.x).contains(&pos.x) && (min.y..=max.y).contains(&pos.y)
+                    }),
+                    "{boundary:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn encounter_substeps_hold_energy_through_close_passes() {
+        // a slowed down binary, small enough to fall through a tight pericentre without touching.
+        let worst_drift = |substep_encounters| {
+            let mut sim = seeded(Scenario::Binary, INIT_SEED);
+            sim.softening = Softening::Off;
+            sim.integrator = Integrator::Leapfrog;
+            sim.substep_encounters = substep_encounters;
+            sim.particles
+                .vel_mut()
+                .iter_mut()
+                .for_each(|vel| *vel *= 0.25);
+            sim.particles.radius_mut().fill(0.5);
+            let energy = |sim: &Simulation| Conservation::measure(sim).total_energy();
+            let start = energy(&sim);
+            (0..6000)
+                .map(|_| {
+                    sim.update(SIM_TIMESTEP.as_secs_f64());
+                    ((energy(&sim) - start) / start).abs()
+                })
+                .fold(0.0, f64::max)
+        };
+        assert!(worst_drift(true) < worst_drift(false) / 5.0);
+    }
+
+    #[test]
+    fn field_potential_keeps_energy_drift_meaningful() {
+        let drift_with = |kind: FieldKind| {
+            let mut sim = Simulation::new();
+            sim.clear();
+            sim.integrator = Integrator::Leapfrog;
+            sim.diagnostics.enabled = true;
+            for field in &mut sim.force_fields {
+                field.enabled = field.kind == kind;
+            }
+            sim.spawn_particle(vec2(0.0, -100.0), vec2(0.1, 0.0), 1.0, Motion::Dynamic, 0.0);
+            for _ in 0..600 {
+                sim.update(SIM_TIMESTEP.as_secs_f64());
+            }
+            sim.diagnostics.drift().unwrap().energy
+        };
+        // falling through a uniform field, all of the kinetic energy gained is accounted for.
+        assert!(drift_with(FieldKind::Gravity).unwrap() < 1e-9);
+        assert_eq!(drift_with(FieldKind::Drag { quadratic: false }), None);
+    }
+
+    #[test]
+    fn prediction_stays_within_pair_budget() {
+        let aim = |sim: &Simulation, background: &mut Option<Background>| {
+            sim.predict_trajectory(
+                background,
+                vec2(100.0, -50.0),
+                vec2(0.0, 0.0),
+                1.0,
+                0.0,
+                PREDICTION_STEPS[2],
+            )
+            .path
+            .len()
+        };
+        let grid = |count: usize| {
+            let mut sim = Simulation::new();
+            sim.clear();
+            for i in 0..count {
+                let pos = vec2((i % 50) as f64 * 4.0, (i / 50) as f64 * 4.0);
+                sim.spawn_particle(pos, vec2(0.0, 0.0), 0.5, Motion::Dynamic, 0.0);
+            }
+            sim
+        };
+
+        // small systems keep ahead of the running simulation, up to the full horizon.
+        let mut sim = grid(100);
+        let mut background = None;
+        let mut previous = 0;
+        for _ in 0..4 {
+            sim.update(SIM_TIMESTEP.as_secs_f64());
+            let len = aim(&sim, &mut background);
+            assert!(len > previous || len == PREDICTION_STEPS[2] + 1);
+            previous = len;
+        }
+        assert_eq!(previous, PREDICTION_STEPS[2] + 1);
+
+        // past the budget, a step is only recorded once enough frames have paid for it.
+        let count = 1500;
+        let sim = grid(count);
+        let mut background = None;
+        let frames = 8;
+        let mut len = 0;
+        for _ in 0..frames {
+            len = aim(&sim, &mut background);
+        }
+        let recorded = len - 1;
+        assert!(recorded > 0);
+        assert!(recorded * count * count <= frames * PREDICTION_PAIR_BUDGET);
+
+        // the real run outpaces the recording, it's rebased without losing what it banked.
+        let mut sim = grid(count);
+        let mut background = None;
+        let longest = (0..frames)
+            .map(|_| {
+                sim.update(SIM_TIMESTEP.as_secs_f64());
+                aim(&sim, &mut background)
+            })
+            .max()
+            .unwrap();
+        assert!(longest > 1);
+    }
+
+    #[test]
+    fn saved_emitters_load_back_identically() {
+        let mut sim = seeded(Scenario::TwoBodies, INIT_SEED);
+        let mut emitter = Emitter::new(vec2(10.0, -20.0), 0.3, INIT_SEED + 7);
+        emitter.placed = true;
+        emitter.edit(1.0, -2.0, 1.0, 3.0);
+        sim.emitters.push(emitter);
+
+        let path = std::env::temp_dir().join("toy_physics_emitters_test.txt");
+        assert_eq!(sim.save_emitters(&path).unwrap(), 1);
+        let mut loaded = seeded(Scenario::TwoBodies, INIT_SEED);
+        assert_eq!(loaded.load_emitters(&path).unwrap(), 1);
+        std::fs::remove_file(&path).unwrap();
+
+        sim.emitters.iter_mut().for_each(Emitter::restart);
+        assert_eq!(sim.state_hash(), loaded.state_hash());
+    }
+
+    #[test]
+    fn unsampleable_emitters_are_rejected_on_load() {
+        let good = Emitter::new(vec2(0.0, 0.0), 0.0, INIT_SEED).to_line();
+        assert!(Emitter::from_line(&good).is_some());
+
+        let with = |index: usize, value: &str| {
+            let mut fields: Vec<&str> = good.split_whitespace().collect();
+            fields[index] = value;
+            fields.join(" ")
+        };
+        let bad = [
+            with(0, "NaN"),
+            with(2, "inf"),
+            with(3, "-0.1"),
+            with(3, "4.0"),
+            with(4, "2.0"),  // speed min past the max.
+            with(6, "-1.0"), // radius min below 0.
+            with(6, "5.0"),  // radius min past the max.
+            with(8, "-inf"),
+            good.rsplit_once(' ').unwrap().0.to_string(), // truncated.
+        ];
+        let path = std::env::temp_dir().join("toy_physics_bad_emitters_test.txt");
+        for line in bad {
+            assert!(Emitter::from_line(&line).is_none(), "{line}");
+
+            std::fs::write(&path, format!("{good}\n{line}")).unwrap();
+            let mut sim = seeded(Scenario::TwoBodies, INIT_SEED);
+            let err = sim.load_emitters(&path).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+            assert!(sim.emitters.is_empty());
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
 }
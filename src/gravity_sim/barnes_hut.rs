@@ -0,0 +1,219 @@
+use super::{gravity_between, gravity_force, particles::Particles, softening::Gravity};
+use crate::utils::*;
+
+// Past this depth, bodies share a leaf rather than subdividing forever (coincident particles).
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Clone)]
+struct Node {
+    centre: Vec2<f64, WorldSpace>,
+    half_size: f64,
+    mass: f64,
+    // sum of mass * pos, divided through by mass once the tree is built.
+    centre_of_mass: Vec2<f64, WorldSpace>,
+    // index of the first of 4 consecutive children (NW, NE, SW, SE), None for leaves.
+    children: Option<usize>,
+    bodies: Vec<usize>,
+}
+
+impl Node {
+    fn new(centre: Vec2<f64, WorldSpace>, half_size: f64) -> Self {
+        Self {
+            centre,
+            half_size,
+            mass: 0.0,
+            centre_of_mass: vec2(0.0, 0.0),
+            children: None,
+            bodies: Vec::new(),
+        }
+    }
+
+    const fn quadrant(&self, pos: Vec2<f64, WorldSpace>) -> usize {
+        (pos.x >= self.centre.x) as usize + 2 * (pos.y >= self.centre.y) as usize
+    }
+
+    fn contains(&self, pos: Vec2<f64, WorldSpace>) -> bool {
+        (pos.x - self.centre.x).abs() <= self.half_size
+            && (pos.y - self.centre.y).abs() <= self.half_size
+    }
+}
+
+// Per particle |a_bh - a_exact|, relative to the RMS exact acceleration across all particles.
+#[derive(Debug, Clone, Copy)]
+pub struct ForceError {
+    pub mean: f64,
+    pub p99: f64, // 99th percentile, a lone outlier can't set it.
+}
+
+// Arena allocated quadtree, rebuilt from scratch every step.
+#[derive(Debug, Clone)]
+pub struct QuadTree {
+    nodes: Vec<Node>,
+}
+
+impl QuadTree {
+    pub fn new(particles: &Particles) -> Self {
+        optick::event!("QuadTree::new");
+
+        let mut min = vec2(f64::MAX, f64::MAX);
+        let mut max = vec2(f64::MIN, f64::MIN);
+        for pos in particles.pos() {
+            min = vec2(min.x.min(pos.x), min.y.min(pos.y));
+            max = vec2(max.x.max(pos.x), max.y.max(pos.y));
+        }
+        let half_size = ((max.x - min.x).max(max.y - min.y) / 2.0).max(1.0);
+        let centre = (min + max) / 2.0;
+
+        let mut tree = Self {
+            nodes: vec![Node::new(centre, half_size)],
+        };
+        for i in 0..particles.len() {
+            tree.insert(0, i, particles, 0);
+        }
+        tree.finalise(0);
+        tree
+    }
+
+    fn insert(&mut self, node: usize, body: usize, particles: &Particles, depth: usize) {
+        let (pos, mass) = (particles.pos()[body], particles.mass()[body]);
+        self.nodes[node].mass += mass;
+        self.nodes[node].centre_of_mass += pos * mass;
+
+        if let Some(first_child) = self.nodes[node].children {
+            let child = first_child + self.nodes[node].quadrant(pos);
+            self.insert(child, body, particles, depth + 1);
+            return;
+        }
+
+        if self.nodes[node].bodies.is_empty() || depth >= MAX_DEPTH {
+            self.nodes[node].bodies.push(body);
+            return;
+        }
+
+        // Occupied leaf, split it and push both bodies down a level.
+        let first_child = self.nodes.len();
+        let quarter = self.nodes[node].half_size / 2.0;
+        let centre = self.nodes[node].centre;
+        for (dx, dy) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            self.nodes.push(Node::new(
+                centre + vec2(dx * quarter, dy * quarter),
+                quarter,
+            ));
+        }
+        self.nodes[node].children = Some(first_child);
+
+        for existing in std::mem::take(&mut self.nodes[node].bodies) {
+            let child = first_child + self.nodes[node].quadrant(particles.pos()[existing]);
+            self.insert_leaf_only(child, existing, particles);
+        }
+        let child = first_child + self.nodes[node].quadrant(pos);
+        self.insert(child, body, particles, depth + 1);
+    }
+
+    // Like insert, but the body's mass is already accounted for in the parent.
+    fn insert_leaf_only(&mut self, node: usize, body: usize, particles: &Particles) {
+        let (pos, mass) = (particles.pos()[body], particles.mass()[body]);
+        self.nodes[node].mass += mass;
+        self.nodes[node].centre_of_mass += pos * mass;
+        self.nodes[node].bodies.push(body);
+    }
+
+    fn finalise(&mut self, node: usize) {
+        if self.nodes[node].mass > 0.0 {
+            let mass = self.nodes[node].mass;
+            self.nodes[node].centre_of_mass /= mass;
+        }
+        if let Some(first_child) = self.nodes[node].children {
+            for child in first_child..first_child + 4 {
+                self.finalise(child);
+            }
+        }
+    }
+
+    // Gravitational force on particles[index] from every other body in the tree.
+    // A node is treated as a point mass when (node width / distance) < theta,
+    // theta = 0 degenerates into the exact O(n^2) sum.
+    pub fn force_on(
+        &self,
+        index: usize,
+        particles: &Particles,
+        theta: f64,
+        gravity: Gravity,
+    ) -> Vec2<f64, WorldSpace> {
+        let (pos, mass, radius) = (particles.pos(), particles.mass(), particles.radius());
+        let (p_pos, p_mass, p_radius) = (pos[index], mass[index], radius[index]);
+        let mut force = vec2(0.0, 0.0);
+        let mut stack = vec![0];
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if node.mass == 0.0 {
+                continue;
+            }
+
+            match node.children {
+                None => {
+                    // overlapping bodies are handled by collisions, not gravity.
+                    for &j in node.bodies.iter().filter(|&&j| j != index) {
+                        force += gravity_between(
+                            p_pos,
+                            p_mass,
+                            pos[j],
+                            mass[j],
+                            p_radius + radius[j],
+                            gravity,
+                        );
+                    }
+                }
+                Some(first_child) => {
+                    // when wrapping, each node's nearest image stands in for the whole node.
+                    let dist = gravity.separation(p_pos, node.centre_of_mass).length();
+                    let width = node.half_size * 2.0;
+                    if !node.contains(p_pos) && width < theta * dist {
+                        force +=
+                            gravity_force(p_pos, p_mass, node.centre_of_mass, node.mass, gravity);
+                    } else {
+                        stack.extend(first_child..first_child + 4);
+                    }
+                }
+            }
+        }
+
+        force
+    }
+
+    // Compares Barnes-Hut accelerations against the exact pairwise sum. Normalised by the RMS
+    // exact acceleration rather than each particle's own, where the net pull nearly cancels a tiny
+    // absolute error would read as hundreds of percent.
+    pub fn force_error(particles: &Particles, theta: f64, gravity: Gravity) -> ForceError {
+        optick::event!("QuadTree::force_error");
+
+        let tree = Self::new(particles);
+        let mass = particles.mass();
+
+        let mut exact_sq = 0.0;
+        let mut errors: Vec<f64> = (0..particles.len())
+            .filter(|&i| mass[i] != 0.0)
+            .map(|i| {
+                let exact = tree.force_on(i, particles, 0.0, gravity) / mass[i];
+                let approx = tree.force_on(i, particles, theta, gravity) / mass[i];
+                exact_sq += exact.dot(exact);
+                (approx - exact).length()
+            })
+            .collect();
+
+        let rms = (exact_sq / errors.len().max(1) as f64).sqrt();
+        if rms == 0.0 {
+            return ForceError {
+                mean: 0.0,
+                p99: 0.0,
+            };
+        }
+        errors.sort_by(f64::total_cmp);
+        let p99 = errors[(errors.len() - 1) * 99 / 100];
+        ForceError {
+            mean: errors.iter().sum::<f64>() / errors.len() as f64 / rms,
+            p99: p99 / rms,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use super::Simulation;
+use crate::utils::*;
+use log::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    Open,       // unbounded, particles beyond the escape radius are culled.
+    Reflective, // walls bounce particles back, scaled by restitution.
+    Periodic,   // toroidal wrap, gravity uses the nearest image of each particle.
+}
+
+impl Boundary {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Open => Self::Reflective,
+            Self::Reflective => Self::Periodic,
+            Self::Periodic => Self::Open,
+        }
+    }
+}
+
+// Axis aligned world rectangle.
+#[derive(Debug, Clone, Copy)]
+pub struct Bounds {
+    pub min: Vec2<f64, WorldSpace>,
+    pub size: Vec2<f64, WorldSpace>,
+}
+
+impl Bounds {
+    pub fn centred(centre: Vec2<f64, WorldSpace>, size: Vec2<f64, WorldSpace>) -> Self {
+        Self {
+            min: centre - size / 2.0,
+            size,
+        }
+    }
+
+    pub fn max(&self) -> Vec2<f64, WorldSpace> {
+        self.min + self.size
+    }
+
+    pub fn centre(&self) -> Vec2<f64, WorldSpace> {
+        self.min + self.size / 2.0
+    }
+
+    // Same point, moved into the bounds by whole world sizes.
+    pub fn wrap(&self, pos: Vec2<f64, WorldSpace>) -> Vec2<f64, WorldSpace> {
+        vec2(
+            self.min.x + (pos.x - self.min.x).rem_euclid(self.size.x),
+            self.min.y + (pos.y - self.min.y).rem_euclid(self.size.y),
+        )
+    }
+
+    // Pushes a circle back inside the walls, bouncing it off whichever it went through.
+    pub fn reflect(
+        &self,
+        pos: &mut Vec2<f64, WorldSpace>,
+        vel: &mut Vec2<f64, WorldSpace>,
+        radius: f64,
+        restitution: f64,
+    ) {
+        let (low, high) = (self.min + radius, self.max() - radius);
+        if pos.x < low.x {
+            pos.x = low.x;
+            vel.x = vel.x.abs() * restitution;
+        } else if pos.x > high.x {
+            pos.x = high.x;
+            vel.x = -vel.x.abs() * restitution;
+        }
+        if pos.y < low.y {
+            pos.y = low.y;
+            vel.y = vel.y.abs() * restitution;
+        } else if pos.y > high.y {
+            pos.y = high.y;
+            vel.y = -vel.y.abs() * restitution;
+        }
+    }
+}
+
+impl Simulation {
+    // Walls & wrapping, applied after integration so positions never leave the bounds.
+    pub fn apply_walls(&mut self) {
+        optick::event!("Simulation::apply_walls");
+
+        let bounds = self.bounds;
+        match self.boundary {
+            Boundary::Open => {}
+            Boundary::Reflective => {
+                for p in self.particles.iter_mut() {
+                    bounds.reflect(p.pos, p.vel, *p.radius, self.restitution);
+                }
+            }
+            Boundary::Periodic => {
+                for p in self.particles.iter_mut() {
+                    let wrapped = bounds.wrap(*p.pos);
+                    // move the previous position too, so interpolation doesn't streak across the world.
+                    *p.prev_pos += wrapped - *p.pos;
+                    *p.pos = wrapped;
+                }
+            }
+        }
+    }
+
+    // Only a periodic world wraps. There, a step longer than half the world has been wrapped, not
+    // travelled, so a line between the two ends would cut across the whole world.
+    pub fn draws_across_wrap(
+        &self,
+        from: Vec2<f64, WorldSpace>,
+        to: Vec2<f64, WorldSpace>,
+    ) -> bool {
+        self.boundary == Boundary::Periodic
+            && (to - from).length() > self.bounds.size.x.min(self.bounds.size.y) / 2.0
+    }
+
+    // Open space only, removes anything further than the escape radius from the centre.
+    pub fn cull_escaped(&mut self) {
+        if self.boundary != Boundary::Open {
+            return;
+        }
+
+        let centre = self.bounds.centre();
+        let escape_radius = self.escape_radius;
+        let (mut count, mut mass) = (0, 0.0);
+        self.particles.retain(|p| {
+            let escaped = (p.pos - centre).length() > escape_radius;
+            if escaped {
+                count += 1;
+                mass += p.mass;
+            }
+            !escaped
+        });
+
+        if count > 0 {
+            info!(
+                "Step {}: culled {count} escaped particle(s), carrying {:.4e} kg",
+                self.step,
+                self.units.mass_to_si(mass)
+            );
+            self.diagnostics.reset();
+        }
+    }
+}
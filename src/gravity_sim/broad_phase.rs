@@ -0,0 +1,210 @@
+use super::{boundary::Bounds, particles::Particles};
+use crate::utils::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadPhase {
+    AllPairs,      // O(n^2) bounding box test, the reference.
+    SpatialHash,   // uniform grid, cell size taken from the average radius.
+    SweepAndPrune, // sort on the x axis, only test overlapping x intervals.
+}
+
+impl BroadPhase {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::AllPairs => Self::SpatialHash,
+            Self::SpatialHash => Self::SweepAndPrune,
+            Self::SweepAndPrune => Self::AllPairs,
+        }
+    }
+
+    // Pairs (i < j) whose bounding boxes overlap. Sorted, so every broad phase
+    // hands contacts to the narrow phase in the same order as the all pairs loop.
+    // `wrap` is the world when it's periodic, boxes then overlap across the seams too.
+    pub fn candidate_pairs(
+        self,
+        particles: &Particles,
+        wrap: Option<Bounds>,
+    ) -> Vec<(usize, usize)> {
+        optick::event!("BroadPhase::candidate_pairs");
+
+        let period = wrap.map(|bounds| bounds.size);
+        let mut pairs = match self {
+            Self::AllPairs => Self::all_pairs(particles, period),
+            Self::SpatialHash => Self::spatial_hash(particles, wrap),
+            Self::SweepAndPrune => Self::sweep_and_prune(particles, wrap),
+        };
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    fn all_pairs(
+        particles: &Particles,
+        period: Option<Vec2<f64, WorldSpace>>,
+    ) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for i in 0..particles.len() {
+            for j in i + 1..particles.len() {
+                if bounds_overlap(particles, i, j, period) {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    fn spatial_hash(particles: &Particles, wrap: Option<Bounds>) -> Vec<(usize, usize)> {
+        if particles.is_empty() {
+            return Vec::new();
+        }
+
+        let avg_radius = particles.radius().iter().sum::<f64>() / particles.len() as f64;
+        let cell_size = (avg_radius * 2.0).max(1.0);
+        let (x_axis, y_axis) = (
+            Axis::new(cell_size, wrap.map(|bounds| (bounds.min.x, bounds.size.x))),
+            Axis::new(cell_size, wrap.map(|bounds| (bounds.min.y, bounds.size.y))),
+        );
+
+        // particles a little larger than a cell are inserted into every cell they touch, huge ones
+        // would fill thousands of cells every step, so they're tested against everything instead.
+        let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        let mut large = Vec::new();
+        for (i, (pos, &radius)) in particles.pos().iter().zip(particles.radius()).enumerate() {
+            if x_axis.cell(pos.x + radius) - x_axis.cell(pos.x - radius) >= SPATIAL_HASH_MAX_SPAN {
+                large.push(i);
+                continue;
+            }
+            for y in y_axis.cells(pos.y - radius, pos.y + radius) {
+                for x in x_axis.cells(pos.x - radius, pos.x + radius) {
+                    grid.entry((x, y)).or_default().push(i);
+                }
+            }
+        }
+
+        let period = wrap.map(|bounds| bounds.size);
+        let mut pairs = Vec::new();
+        for &i in &large {
+            for j in (0..particles.len()).filter(|&j| j != i) {
+                if bounds_overlap(particles, i, j, period) {
+                    pairs.push((i.min(j), i.max(j)));
+                }
+            }
+        }
+        for cell in grid.values() {
+            for (a, &i) in cell.iter().enumerate() {
+                for &j in &cell[a + 1..] {
+                    if bounds_overlap(particles, i, j, period) {
+                        pairs.push((i.min(j), i.max(j)));
+                    }
+                }
+            }
+        }
+        pairs
+    }
+
+    fn sweep_and_prune(particles: &Particles, wrap: Option<Bounds>) -> Vec<(usize, usize)> {
+        let period = wrap.map(|bounds| bounds.size);
+        let (pos, radius) = (particles.pos(), particles.radius());
+        let mut order: Vec<usize> = (0..particles.len()).collect();
+        order.sort_unstable_by(|&a, &b| (pos[a].x - radius[a]).total_cmp(&(pos[b].x - radius[b])));
+
+        let mut pairs = Vec::new();
+        let mut active: Vec<usize> = Vec::new();
+        for &i in &order {
+            // anything ending before this one starts can never overlap a later particle.
+            active.retain(|&j| pos[j].x + radius[j] >= pos[i].x - radius[i]);
+            for &j in &active {
+                if bounds_overlap(particles, i, j, period) {
+                    pairs.push((i.min(j), i.max(j)));
+                }
+            }
+            active.push(i);
+        }
+
+        // the sweep can't see round the x seam, so anything within reach of the left edge of the
+        // world is also tested against everything within reach of the right edge.
+        if let Some(bounds) = wrap {
+            let reach = 2.0 * radius.iter().copied().fold(0.0, f64::max);
+            let (left, right): (Vec<usize>, Vec<usize>) = (
+                order
+                    .iter()
+                    .copied()
+                    .filter(|&i| pos[i].x < bounds.min.x + reach)
+                    .collect(),
+                order
+                    .iter()
+                    .copied()
+                    .filter(|&i| pos[i].x > bounds.max().x - reach)
+                    .collect(),
+            );
+            for &i in &left {
+                for &j in right.iter().filter(|&&j| j != i) {
+                    if bounds_overlap(particles, i, j, period) {
+                        pairs.push((i.min(j), i.max(j)));
+                    }
+                }
+            }
+        }
+        pairs
+    }
+}
+
+// One axis of the spatial hash grid. In a periodic world it's stretched to a whole number of
+// cells, so the cells either side of a seam are neighbours like any others.
+#[derive(Debug, Clone, Copy)]
+struct Axis {
+    origin: f64,
+    cell_size: f64,
+    count: Option<i64>, // cells across the world when wrapping.
+}
+
+impl Axis {
+    fn new(cell_size: f64, wrap: Option<(f64, f64)>) -> Self {
+        match wrap {
+            None => Self {
+                origin: 0.0,
+                cell_size,
+                count: None,
+            },
+            Some((min, size)) => {
+                let count = (size / cell_size).floor().max(1.0) as i64;
+                Self {
+                    origin: min,
+                    cell_size: size / count as f64,
+                    count: Some(count),
+                }
+            }
+        }
+    }
+
+    fn cell(self, n: f64) -> i64 {
+        ((n - self.origin) / self.cell_size).floor() as i64
+    }
+
+    // Every cell low..=high touches, once each even if it's wider than the world.
+    fn cells(self, low: f64, high: f64) -> impl Iterator<Item = i64> {
+        let (first, last) = (self.cell(low), self.cell(high));
+        let last = self.count.map_or(last, |count| last.min(first + count - 1));
+        (first..=last).map(move |cell| self.count.map_or(cell, |count| cell.rem_euclid(count)))
+    }
+}
+
+// Compares the nearest images when wrapping, like Gravity::separation.
+fn bounds_overlap(
+    particles: &Particles,
+    i: usize,
+    j: usize,
+    period: Option<Vec2<f64, WorldSpace>>,
+) -> bool {
+    let (pos, radius) = (particles.pos(), particles.radius());
+    let reach = radius[i] + radius[j];
+    let mut dist = pos[j] - pos[i];
+    if let Some(period) = period {
+        dist = vec2(
+            dist.x - period.x * (dist.x / period.x).round(),
+            dist.y - period.y * (dist.y / period.y).round(),
+        );
+    }
+    dist.x.abs() <= reach && dist.y.abs() <= reach
+}
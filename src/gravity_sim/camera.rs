@@ -0,0 +1,148 @@
+use super::{lerp, particles::Particles, ParticleSlices};
+use crate::utils::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Follow {
+    Free,                 // plain world frame, only WASD moves the view.
+    Particle(u64),        // keeps the particle with this id at the same spot on screen.
+    CentreOfMass,         // keeps the centre of mass of everything at the same spot.
+    CoRotating(u64, u64), // on the pair's barycentre, turning so the line between them stays put.
+}
+
+// Moving, rotating reference frame the camera looks through.
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    pub origin: Vec2<f64, WorldSpace>,
+    pub angle: f64, // radians, from the world x axis towards the world y axis.
+}
+
+// World to render transform, render = ((world - origin).rotate(-angle) - pos) * zoom.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    pub pos: Vec2<f64, WorldSpace>, // top left of the viewport relative to the frame, WASD moves this.
+    pub vel: Vec2<f64, RenderSpace>, // pan velocity in pixels per frame, so panning feels the same at any zoom.
+    pub zoom: f64,                   // render pixels per world unit.
+    pub frame: Frame,
+    pub follow: Follow,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            pos: vec2(0.0, 0.0),
+            vel: vec2(0.0, 0.0),
+            zoom: 1.0,
+            frame: Frame {
+                origin: vec2(0.0, 0.0),
+                angle: 0.0,
+            },
+            follow: Follow::Free,
+        }
+    }
+
+    pub fn to_render(self, pos: Vec2<f64, WorldSpace>) -> Vec2<f64, RenderSpace> {
+        let relative = (pos - self.frame.origin).rotate(-self.frame.angle);
+        ((relative - self.pos) * self.zoom).cast_unit()
+    }
+
+    // Truncated to the pixel grid, for drawing.
+    pub fn to_pixel(self, pos: Vec2<f64, WorldSpace>) -> Vec2<i32, RenderSpace> {
+        self.to_render(pos).map(|n| n as i32)
+    }
+
+    pub fn to_world(self, pos: Vec2<f64, RenderSpace>) -> Vec2<f64, WorldSpace> {
+        let relative = pos.cast_unit() / self.zoom + self.pos;
+        relative.rotate(self.frame.angle) + self.frame.origin
+    }
+
+    // A direction drawn on screen (already in world units), turned to match the frame.
+    pub fn to_world_direction(self, dir: Vec2<f64, WorldSpace>) -> Vec2<f64, WorldSpace> {
+        dir.rotate(self.frame.angle)
+    }
+
+    // World position at the middle of a viewport of size render pixels.
+    pub fn centre(self, size: Vec2<i32, RenderSpace>) -> Vec2<f64, WorldSpace> {
+        self.to_world(size.cast() / 2.0)
+    }
+
+    // Scales by factor while keeping the world point under anchor (render space) fixed.
+    pub fn zoom_at(&mut self, anchor: Vec2<f64, RenderSpace>, factor: f64) {
+        let anchor_relative = anchor.cast_unit() / self.zoom + self.pos;
+        self.zoom = (self.zoom * factor).clamp(CAMERA_MIN_ZOOM, CAMERA_MAX_ZOOM);
+        self.pos = anchor_relative - anchor.cast_unit() / self.zoom;
+    }
+
+    // Tracked modes start with the target in the middle of a viewport of size render pixels,
+    // going free keeps whatever is in the middle right now, just unrotated.
+    pub fn set_follow(&mut self, follow: Follow, size: Vec2<i32, RenderSpace>) {
+        let half_view = size.cast().cast_unit() / (2.0 * self.zoom);
+        if follow == Follow::Free {
+            let centre = self.centre(size);
+            self.frame = Frame {
+                origin: vec2(0.0, 0.0),
+                angle: 0.0,
+            };
+            self.pos = centre - half_view;
+        } else {
+            self.pos = half_view * -1.0;
+        }
+        self.follow = follow;
+    }
+
+    // velocity is bounded by equilibrium point with resistance
+    // TODO(TOM): Change CAMERA_RESISTANCE to an easing function?
+    pub fn update(&mut self) {
+        self.vel *= CAMERA_RESISTANCE;
+        self.pos += self.vel.cast_unit() / self.zoom;
+    }
+
+    // Moves the frame with whatever is followed, at alpha between steps like the particles.
+    // Losing the target (merged, culled, cleared) drops back to free, leaving the view where it was.
+    pub(super) fn track(
+        &mut self,
+        particles: &Particles,
+        alpha: f64,
+        size: Vec2<i32, RenderSpace>,
+    ) {
+        let ParticleSlices {
+            id,
+            pos,
+            prev_pos,
+            mass,
+            ..
+        } = particles.slices();
+        let index_of = |target: u64| id.iter().position(|&id| id == target);
+        let pos_at = |i: usize| lerp(prev_pos[i], pos[i], alpha);
+
+        let frame = match self.follow {
+            Follow::Free => return,
+            Follow::Particle(target) => index_of(target).map(|i| Frame {
+                origin: pos_at(i),
+                angle: 0.0,
+            }),
+            Follow::CentreOfMass => {
+                let total_mass: f64 = mass.iter().sum();
+                (total_mass > 0.0).then(|| {
+                    let moment =
+                        (0..pos.len()).fold(vec2(0.0, 0.0), |sum, i| sum + pos_at(i) * mass[i]);
+                    Frame {
+                        origin: moment / total_mass,
+                        angle: 0.0,
+                    }
+                })
+            }
+            Follow::CoRotating(a, b) => index_of(a).zip(index_of(b)).map(|(a, b)| {
+                let axis = pos_at(b) - pos_at(a);
+                Frame {
+                    origin: (pos_at(a) * mass[a] + pos_at(b) * mass[b]) / (mass[a] + mass[b]),
+                    angle: axis.y.atan2(axis.x),
+                }
+            }),
+        };
+
+        match frame {
+            Some(frame) => self.frame = frame,
+            None => self.set_follow(Follow::Free, size),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use super::{particles::Particles, ParticleSlices};
+use crate::utils::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColourBy {
+    Plain,         // everything WHITE.
+    Speed,         // |vel|
+    Mass,          // log10, masses span orders of magnitude.
+    KineticEnergy, // log10 of 1/2 m v^2.
+    Acceleration,  // |acc|
+    Collisions,    // contacts resolved since the particle was created.
+    Charge,        // signed, reads best on the diverging gradient.
+}
+
+impl ColourBy {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Plain => Self::Speed,
+            Self::Speed => Self::Mass,
+            Self::Mass => Self::KineticEnergy,
+            Self::KineticEnergy => Self::Acceleration,
+            Self::Acceleration => Self::Collisions,
+            Self::Collisions => Self::Charge,
+            Self::Charge => Self::Plain,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gradient {
+    Viridis,   // perceptually uniform, dark purple -> yellow.
+    Inferno,   // perceptually uniform, black -> pale yellow, reads well on the dark background.
+    Diverging, // blue -> grey -> red around the middle of the range.
+}
+
+impl Gradient {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Viridis => Self::Inferno,
+            Self::Inferno => Self::Diverging,
+            Self::Diverging => Self::Viridis,
+        }
+    }
+
+    // evenly spaced stops, t is clamped to 0..1.
+    const fn stops(self) -> &'static [Rgba] {
+        match self {
+            Self::Viridis => &VIRIDIS,
+            Self::Inferno => &INFERNO,
+            Self::Diverging => &DIVERGING,
+        }
+    }
+
+    pub fn sample(self, t: f64) -> Rgba {
+        let stops = self.stops();
+        let scaled = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let i = (scaled as usize).min(stops.len() - 2);
+        stops[i].lerp(stops[i + 1], scaled - i as f64)
+    }
+}
+
+const VIRIDIS: [Rgba; 5] = [
+    Rgba::from_rgb(68, 1, 84),
+    Rgba::from_rgb(59, 82, 139),
+    Rgba::from_rgb(33, 145, 140),
+    Rgba::from_rgb(94, 201, 98),
+    Rgba::from_rgb(253, 231, 37),
+];
+const INFERNO: [Rgba; 6] = [
+    Rgba::from_rgb(0, 0, 4),
+    Rgba::from_rgb(66, 10, 104),
+    Rgba::from_rgb(147, 38, 103),
+    Rgba::from_rgb(221, 81, 58),
+    Rgba::from_rgb(252, 165, 10),
+    Rgba::from_rgb(252, 255, 164),
+];
+const DIVERGING: [Rgba; 3] = [
+    Rgba::from_rgb(59, 76, 192),
+    Rgba::from_rgb(221, 221, 221),
+    Rgba::from_rgb(180, 4, 38),
+];
+
+// Shades each particle from one scalar attribute.
+#[derive(Debug, Clone, Copy)]
+pub struct ColourMap {
+    pub colour_by: ColourBy,
+    pub gradient: Gradient,
+    pub range: Option<(f64, f64)>, // fixed (min, max) of the attribute, None == auto range each frame.
+}
+
+impl ColourMap {
+    pub const fn new() -> Self {
+        Self {
+            colour_by: ColourBy::Plain,
+            gradient: Gradient::Viridis,
+            range: None,
+        }
+    }
+
+    // One colour per particle, in particle order.
+    pub(super) fn colours(&self, particles: &Particles) -> Vec<Rgba> {
+        optick::event!("ColourMap::colours");
+
+        if self.colour_by == ColourBy::Plain {
+            return vec![WHITE; particles.len()];
+        }
+
+        let values = self.values(particles);
+        let (min, max) = self.range.unwrap_or_else(|| Self::auto_range(&values));
+        values
+            .iter()
+            .map(|&value| {
+                // flat range (e.g. a single particle), park everything in the middle.
+                let t = if max > min {
+                    (value - min) / (max - min)
+                } else {
+                    0.5
+                };
+                self.gradient.sample(t)
+            })
+            .collect()
+    }
+
+    // Current auto range, for freezing into a fixed one.
+    pub(super) fn measure_range(&self, particles: &Particles) -> (f64, f64) {
+        Self::auto_range(&self.values(particles))
+    }
+
+    fn values(&self, particles: &Particles) -> Vec<f64> {
+        let ParticleSlices {
+            vel,
+            acc,
+            mass,
+            collisions,
+            charge,
+            ..
+        } = particles.slices();
+        match self.colour_by {
+            ColourBy::Plain => vec![0.0; particles.len()],
+            ColourBy::Speed => vel.iter().map(|v| v.length()).collect(),
+            ColourBy::Mass => mass.iter().map(|m| m.log10()).collect(),
+            ColourBy::KineticEnergy => vel
+                .iter()
+                .zip(mass)
+                .map(|(v, m)| (0.5 * m * v.dot(*v)).log10())
+                .collect(),
+            ColourBy::Acceleration => acc.iter().map(|a| a.length()).collect(),
+            ColourBy::Collisions => collisions.iter().map(|&n| n as f64).collect(),
+            ColourBy::Charge => charge.to_vec(),
+        }
+    }
+
+    // log10(0) is -inf for bodies at rest, those are ignored rather than stretching the range.
+    fn auto_range(values: &[f64]) -> (f64, f64) {
+        let (min, max) = values
+            .iter()
+            .filter(|n| n.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &n| {
+                (min.min(n), max.max(n))
+            });
+        if min > max {
+            (0.0, 0.0)
+        } else {
+            (min, max)
+        }
+    }
+}
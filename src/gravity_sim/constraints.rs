@@ -0,0 +1,303 @@
+use super::{
+    create_particle, lerp, motion::Motion, GravitySim, ParticleSlices, ParticleSlicesMut,
+    Simulation,
+};
+use crate::utils::*;
+use log::trace;
+use std::{collections::HashMap, hash::Hasher};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkKind {
+    Spring {
+        stiffness: f64, // force per unit stretch.
+        damping: f64,   // force per unit closing speed along the link.
+    },
+    Rod, // fixed length, projected back after every step.
+}
+
+// Connection between two particles, by id so culling & merging don't mix them up.
+#[derive(Debug, Clone, Copy)]
+pub struct Link {
+    pub a: u64,
+    pub b: u64,
+    pub kind: LinkKind,
+    pub rest_length: f64,
+    pub break_strain: Option<f64>, // snaps when stretched past rest_length * (1 + strain).
+}
+
+#[derive(Debug, Clone)]
+pub struct Constraints {
+    pub links: Vec<Link>,
+    pub iterations: usize, // rod projection passes per step, more == stiffer chains.
+}
+
+impl Constraints {
+    pub fn new() -> Self {
+        Self {
+            links: Vec::new(),
+            iterations: CONSTRAINT_ITERATIONS,
+        }
+    }
+
+    // (index of a, index of b, link) for every link whose particles both still exist.
+    fn resolve(&self, ids: &[u64]) -> Vec<(usize, usize, Link)> {
+        if self.links.is_empty() {
+            return Vec::new();
+        }
+        let index: HashMap<u64, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+        self.links
+            .iter()
+            .filter_map(|&link| Some((*index.get(&link.a)?, *index.get(&link.b)?, link)))
+            .collect()
+    }
+
+    pub fn hash(&self, hasher: &mut StateHasher) {
+        hasher.write_usize(self.links.len());
+        for link in &self.links {
+            hasher.write_u64(link.a);
+            hasher.write_u64(link.b);
+            hasher.write_f64(link.rest_length);
+            hasher.write_f64(link.break_strain.unwrap_or(-1.0));
+            match link.kind {
+                LinkKind::Spring { stiffness, damping } => {
+                    hasher.write_f64(stiffness);
+                    hasher.write_f64(damping);
+                }
+                LinkKind::Rod => hasher.write_u8(0),
+            }
+        }
+    }
+}
+
+// What a left click drops into the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Brush {
+    Particle, // a single particle, the default.
+    Jelly,    // square lattice of springs, braced across the diagonals so it holds its shape.
+    Truss,    // the same lattice with breakable rods, rigid until it snaps.
+    Rope,     // a line of rods, pinned at the first particle.
+}
+
+impl Brush {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Particle => Self::Jelly,
+            Self::Jelly => Self::Truss,
+            Self::Truss => Self::Rope,
+            Self::Rope => Self::Particle,
+        }
+    }
+}
+
+impl Simulation {
+    // Hookean springs with damping along the link, added on top of the other forces.
+    pub(super) fn update_forces_springs(&mut self) {
+        let pairs = self.constraints.resolve(self.particles.id());
+        let gravity = self.gravity();
+        let ParticleSlicesMut {
+            pos, vel, force, ..
+        } = self.particles.slices_mut();
+
+        for (a, b, link) in pairs {
+            let LinkKind::Spring { stiffness, damping } = link.kind else {
+                continue;
+            };
+            let dist = gravity.separation(pos[a], pos[b]);
+            let length = dist.length();
+            if length == 0.0 {
+                continue;
+            }
+            let normal = dist / length;
+            let closing_speed = (vel[b] - vel[a]).dot(normal);
+            let pull = normal * (stiffness * (length - link.rest_length) + damping * closing_speed);
+            force[a] += pull;
+            force[b] -= pull;
+        }
+    }
+
+    // Energy stored in the springs, None while a damped spring or a rod is losing energy that
+    // no potential accounts for.
+    pub(super) fn link_potential(&self) -> Option<f64> {
+        let gravity = self.gravity();
+        let pos = self.particles.pos();
+        let mut potential = 0.0;
+        for (a, b, link) in self.constraints.resolve(self.particles.id()) {
+            let LinkKind::Spring { stiffness, damping } = link.kind else {
+                return None;
+            };
+            if damping != 0.0 {
+                return None;
+            }
+            let stretch = gravity.separation(pos[a], pos[b]).length() - link.rest_length;
+            potential += 0.5 * stiffness * stretch * stretch;
+        }
+        Some(potential)
+    }
+
+    // Snaps overstretched links, then projects rods back to length & removes their stretching
+    // velocity. Scripted bodies have no inverse mass, so a rope pinned to one hangs from it.
+    pub(super) fn apply_constraints(&mut self) {
+        optick::event!("Simulation::apply_constraints");
+
+        let gravity = self.gravity();
+        let strain = |pos: &[Vec2<f64, WorldSpace>], (a, b, link): &(usize, usize, Link)| {
+            gravity.separation(pos[*a], pos[*b]).length() / link.rest_length - 1.0
+        };
+
+        // dead particles take their links with them, overstretched ones break.
+        let pairs = self.constraints.resolve(self.particles.id());
+        let count = self.constraints.links.len();
+        self.constraints.links = pairs
+            .iter()
+            .filter(|pair| {
+                pair.2
+                    .break_strain
+                    .is_none_or(|limit| strain(self.particles.pos(), pair) <= limit)
+            })
+            .map(|pair| pair.2)
+            .collect();
+        if self.constraints.links.len() < count {
+            trace!(
+                "Step {}: {} links broken or orphaned",
+                self.step,
+                count - self.constraints.links.len()
+            );
+        }
+
+        let rods: Vec<_> = self
+            .constraints
+            .resolve(self.particles.id())
+            .into_iter()
+            .filter(|(.., link)| link.kind == LinkKind::Rod)
+            .collect();
+        if rods.is_empty() {
+            return;
+        }
+        let inverse_mass: Vec<f64> = self.particles.rows().map(|p| p.inverse_mass()).collect();
+        let ParticleSlicesMut { pos, vel, .. } = self.particles.slices_mut();
+
+        for _ in 0..self.constraints.iterations {
+            for &(a, b, link) in &rods {
+                let weight = inverse_mass[a] + inverse_mass[b];
+                let dist = gravity.separation(pos[a], pos[b]);
+                let length = dist.length();
+                if weight == 0.0 || length == 0.0 {
+                    continue;
+                }
+                let correction = dist * ((length - link.rest_length) / (length * weight));
+                pos[a] += correction * inverse_mass[a];
+                pos[b] -= correction * inverse_mass[b];
+            }
+        }
+        for &(a, b, _) in &rods {
+            let weight = inverse_mass[a] + inverse_mass[b];
+            let dist = gravity.separation(pos[a], pos[b]);
+            let length = dist.length();
+            if weight == 0.0 || length == 0.0 {
+                continue;
+            }
+            let normal = dist / length;
+            let impulse = normal * ((vel[b] - vel[a]).dot(normal) / weight);
+            vel[a] += impulse * inverse_mass[a];
+            vel[b] -= impulse * inverse_mass[b];
+        }
+    }
+
+    // size x size grid (size x 1 for ropes) around centre, spacing keeps neighbours just apart.
+    pub(super) fn spawn_lattice(
+        &mut self,
+        centre: Vec2<f64, WorldSpace>,
+        vel: Vec2<f64, WorldSpace>,
+        radius: f64,
+        size: usize,
+        brush: Brush,
+    ) {
+        let density = self.units.density_from_si(EARTH_DENSITY);
+        let spacing = radius * LATTICE_SPACING;
+        let rows = if brush == Brush::Rope { 1 } else { size };
+        let origin = centre - vec2((size - 1) as f64, (rows - 1) as f64) * (spacing / 2.0);
+
+        let first = self.next_id;
+        for y in 0..rows {
+            for x in 0..size {
+                let pos = origin + vec2(x as f64, y as f64) * spacing;
+                let mut particle = create_particle(pos, vel, radius, density);
+                // the rope hangs from its first particle.
+                if brush == Brush::Rope && x == 0 {
+                    particle.motion = Motion::Static;
+                }
+                self.add_particle(particle);
+            }
+        }
+
+        let mass = self.particles.mass().last().copied().unwrap_or_default();
+        let (kind, break_strain) = match brush {
+            Brush::Jelly => (
+                LinkKind::Spring {
+                    stiffness: SPRING_STIFFNESS * mass,
+                    damping: SPRING_DAMPING * mass,
+                },
+                Some(SPRING_BREAK_STRAIN),
+            ),
+            Brush::Truss => (LinkKind::Rod, Some(ROD_BREAK_STRAIN)),
+            Brush::Particle | Brush::Rope => (LinkKind::Rod, None),
+        };
+        let id = |x: usize, y: usize| first + (y * size + x) as u64;
+        let mut link = |a: u64, b: u64, rest_length: f64| {
+            self.constraints.links.push(Link {
+                a,
+                b,
+                kind,
+                rest_length,
+                break_strain,
+            });
+        };
+        let diagonal = spacing * std::f64::consts::SQRT_2;
+        for y in 0..rows {
+            for x in 0..size {
+                if x + 1 < size {
+                    link(id(x, y), id(x + 1, y), spacing);
+                }
+                if y + 1 < rows {
+                    link(id(x, y), id(x, y + 1), spacing);
+                    // bracing, without it a square lattice folds flat.
+                    if x + 1 < size {
+                        link(id(x, y), id(x + 1, y + 1), diagonal);
+                        link(id(x + 1, y), id(x, y + 1), diagonal);
+                    }
+                }
+            }
+        }
+        self.diagnostics.reset();
+    }
+}
+
+impl GravitySim {
+    // Under the particles, like trails.
+    pub fn render_links(&mut self, alpha: f64) {
+        optick::event!("Rendering Links");
+
+        let camera = self.camera;
+        let simulation = &self.simulation;
+        let ParticleSlices {
+            id, pos, prev_pos, ..
+        } = self.simulation.particles.slices();
+
+        for (a, b, link) in self.simulation.constraints.resolve(id) {
+            let (from, to) = (
+                lerp(prev_pos[a], pos[a], alpha),
+                lerp(prev_pos[b], pos[b], alpha),
+            );
+            if simulation.draws_across_wrap(from, to) {
+                continue;
+            }
+            let colour = match link.kind {
+                LinkKind::Spring { .. } => GRAY,
+                LinkKind::Rod => BLUE,
+            };
+            Shape::draw_line(camera.to_pixel(from), camera.to_pixel(to), &mut |x, y| {
+                self.plot_clipped(vec2(x, y), colour)
+            });
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use super::{ParticleSlices, Simulation};
+use crate::utils::*;
+use std::collections::VecDeque;
+
+// Conserved quantities of the whole system at the end of a step.
+#[derive(Debug, Clone, Copy)]
+pub struct Conservation {
+    pub step: usize,
+    pub kinetic_energy: f64,
+    pub potential_energy: f64,
+    pub momentum: Vec2<f64, WorldSpace>,
+    pub angular_momentum: f64, // about the world origin, z component.
+    pub centre_of_mass: Vec2<f64, WorldSpace>,
+    pub mass: f64,
+    pub conservative: bool, // false while a field or link does work no potential accounts for.
+
+    // magnitude scales, so drift stays meaningful when a total sums to ~0 (e.g. momentum at rest).
+    momentum_scale: f64,
+    angular_momentum_scale: f64,
+}
+
+impl Conservation {
+    pub fn measure(sim: &Simulation) -> Self {
+        optick::event!("Conservation::measure");
+
+        let (gravity, forces) = (sim.gravity(), sim.forces);
+        let external = sim
+            .field_potential()
+            .zip(sim.link_potential())
+            .map(|(fields, links)| fields + links);
+        let mut measured = Self {
+            step: sim.step,
+            kinetic_energy: 0.0,
+            potential_energy: external.unwrap_or(0.0),
+            momentum: vec2(0.0, 0.0),
+            angular_momentum: 0.0,
+            centre_of_mass: vec2(0.0, 0.0),
+            mass: 0.0,
+            conservative: external.is_some(),
+            momentum_scale: 0.0,
+            angular_momentum_scale: 0.0,
+        };
+
+        let slices = sim.particles.slices();
+        let ParticleSlices {
+            pos,
+            vel,
+            mass,
+            charge,
+            ..
+        } = slices;
+        for i in 0..pos.len() {
+            let momentum = vel[i] * mass[i];
+            let angular_momentum = pos[i].x * momentum.y - pos[i].y * momentum.x;
+
+            measured.kinetic_energy += 0.5 * mass[i] * vel[i].dot(vel[i]);
+            measured.potential_energy += forces.field_potential(pos[i], charge[i]);
+            measured.momentum += momentum;
+            measured.angular_momentum += angular_momentum;
+            measured.centre_of_mass += pos[i] * mass[i];
+            measured.mass += mass[i];
+            measured.momentum_scale += momentum.length();
+            measured.angular_momentum_scale += angular_momentum.abs();
+
+            for j in i + 1..pos.len() {
+                measured.potential_energy += forces.pair_potential(&slices, i, j, gravity);
+            }
+        }
+
+        if measured.mass > 0.0 {
+            measured.centre_of_mass /= measured.mass;
+        }
+        measured
+    }
+
+    pub fn total_energy(&self) -> f64 {
+        self.kinetic_energy + self.potential_energy
+    }
+}
+
+// Relative change since the baseline, 0.0 == perfectly conserved.
+#[derive(Debug, Clone, Copy)]
+pub struct Drift {
+    pub energy: Option<f64>, // None when something non-conservative acted since the baseline.
+    pub momentum: f64,
+    pub angular_momentum: f64,
+    pub centre_of_mass: f64, // world units, the centre of mass moves if momentum is non-zero.
+}
+
+impl Drift {
+    // For the logs, "n/a" when energy drift would mostly be the work of non-conservative forces.
+    pub fn energy_label(&self) -> String {
+        self.energy
+            .map_or_else(|| "n/a".to_string(), |energy| format!("{energy:.2e}"))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+    pub enabled: bool,
+    initial: Option<Conservation>,
+    history: VecDeque<Conservation>, // ring buffer, oldest first.
+    conservative: bool,              // every sample since the baseline was.
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            initial: None,
+            history: VecDeque::with_capacity(DIAGNOSTICS_HISTORY),
+            conservative: true,
+        }
+    }
+
+    pub fn record(&mut self, measured: Conservation) {
+        self.conservative &= measured.conservative;
+        if self.initial.is_none() {
+            self.initial = Some(measured);
+        }
+
+        if self.history.len() == DIAGNOSTICS_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(measured);
+    }
+
+    // Particles were added or removed outside of the physics, the old baseline is meaningless.
+    pub fn reset(&mut self) {
+        self.initial = None;
+        self.history.clear();
+        self.conservative = true;
+    }
+
+    pub fn initial(&self) -> Option<&Conservation> {
+        self.initial.as_ref()
+    }
+
+    pub fn latest(&self) -> Option<&Conservation> {
+        self.history.back()
+    }
+
+    pub fn history(&self) -> impl Iterator<Item = &Conservation> {
+        self.history.iter()
+    }
+
+    pub fn drift(&self) -> Option<Drift> {
+        let initial = self.initial?;
+        let latest = self.latest()?;
+
+        let relative = |delta: f64, scale: f64| {
+            if scale == 0.0 {
+                0.0
+            } else {
+                delta.abs() / scale
+            }
+        };
+
+        Some(Drift {
+            energy: self.conservative.then(|| {
+                relative(
+                    latest.total_energy() - initial.total_energy(),
+                    initial.total_energy().abs(),
+                )
+            }),
+            momentum: relative(
+                (latest.momentum - initial.momentum).length(),
+                initial.momentum_scale,
+            ),
+            angular_momentum: relative(
+                latest.angular_momentum - initial.angular_momentum,
+                initial.angular_momentum_scale,
+            ),
+            centre_of_mass: (latest.centre_of_mass - initial.centre_of_mass).length(),
+        })
+    }
+}
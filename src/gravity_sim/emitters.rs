@@ -0,0 +1,268 @@
+use super::{create_particle, GravitySim, Simulation};
+use crate::utils::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{collections::HashSet, fs, hash::Hasher, io, path::Path};
+
+// Continuous source of particles, part of the scene like the particles it makes. Speed & radius
+// are in default units like the spawn brush, converted into whatever units the scene is in.
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    pub pos: Vec2<f64, WorldSpace>,
+    pub direction: f64, // radians, from the world x axis towards the world y axis.
+    pub spread: f64,    // half angle of the cone particles leave in.
+    pub speed: (f64, f64),
+    pub radius: (f64, f64),
+    pub rate: f64,  // particles per time unit.
+    pub cap: usize, // live particles from this emitter, it pauses while at the cap.
+    pub enabled: bool,
+    pub placed: bool, // from the UI, kept through resets & clears unlike a scenario's own.
+    due: f64,         // fraction of a particle owed from previous steps.
+    emitted: Vec<u64>, // ids, pruned as they merge or get culled.
+    seed: u64,        // restart replays the stream from here.
+    rng: StdRng,      // own stream, so adding an emitter doesn't reshuffle the others.
+    draws: u64,       // taken from rng since the seed, stands in for its state when hashing.
+}
+
+impl Emitter {
+    pub fn new(pos: Vec2<f64, WorldSpace>, direction: f64, seed: u64) -> Self {
+        Self {
+            pos,
+            direction,
+            spread: EMITTER_SPREAD,
+            speed: EMITTER_SPEED,
+            radius: EMITTER_RADIUS,
+            rate: EMITTER_RATE,
+            cap: EMITTER_CAP,
+            enabled: true,
+            placed: false,
+            due: 0.0,
+            emitted: Vec::new(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            draws: 0,
+        }
+    }
+
+    // Back to how it was placed, so the stream after a reset replays exactly.
+    pub fn restart(&mut self) {
+        self.due = 0.0;
+        self.emitted.clear();
+        self.rng = StdRng::seed_from_u64(self.seed);
+        self.draws = 0;
+    }
+
+    pub fn live(&self) -> usize {
+        self.emitted.len()
+    }
+
+    // Scales rate & speed by rate_steps & speed_steps powers of the inspector step, radius the
+    // same with radius_steps, and widens spread by spread_steps of the rotate step.
+    pub fn edit(
+        &mut self,
+        rate_steps: f64,
+        speed_steps: f64,
+        radius_steps: f64,
+        spread_steps: f64,
+    ) {
+        let speed_scale = INSPECTOR_SCALE_STEP.powf(speed_steps);
+        let radius_scale = INSPECTOR_SCALE_STEP.powf(radius_steps);
+        self.rate *= INSPECTOR_SCALE_STEP.powf(rate_steps);
+        self.speed = (self.speed.0 * speed_scale, self.speed.1 * speed_scale);
+        self.radius = (self.radius.0 * radius_scale, self.radius.1 * radius_scale);
+        self.spread =
+            (self.spread + spread_steps * INSPECTOR_ROTATE_STEP).clamp(0.0, std::f64::consts::PI);
+    }
+
+    // One line of whitespace separated fields, the format emitters are saved in.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} {} {} {} {} {} {} {} {} {} {} {}",
+            self.pos.x,
+            self.pos.y,
+            self.direction,
+            self.spread,
+            self.speed.0,
+            self.speed.1,
+            self.radius.0,
+            self.radius.1,
+            self.rate,
+            self.cap,
+            self.enabled,
+            self.seed,
+        )
+    }
+
+    // Inverse of to_line, the stream starts over from the saved seed. None for anything
+    // run_emitters can't sample from: non-finite values, inverted ranges or a cone past pi.
+    pub fn from_line(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [x, y, direction, spread, speed_min, speed_max, radius_min, radius_max, rate, cap, enabled, seed] =
+            fields[..]
+        else {
+            return None;
+        };
+        let float = |field: &str| field.parse::<f64>().ok().filter(|value| value.is_finite());
+        let mut emitter = Self::new(
+            vec2(float(x)?, float(y)?),
+            float(direction)?,
+            seed.parse().ok()?,
+        );
+        emitter.spread = float(spread)?;
+        emitter.speed = (float(speed_min)?, float(speed_max)?);
+        emitter.radius = (float(radius_min)?, float(radius_max)?);
+        emitter.rate = float(rate)?;
+        emitter.cap = cap.parse().ok()?;
+        emitter.enabled = enabled.parse().ok()?;
+
+        let valid = (0.0..=std::f64::consts::PI).contains(&emitter.spread)
+            && 0.0 <= emitter.speed.0
+            && emitter.speed.0 <= emitter.speed.1
+            && 0.0 < emitter.radius.0
+            && emitter.radius.0 <= emitter.radius.1
+            && emitter.rate >= 0.0;
+        valid.then_some(emitter)
+    }
+
+    pub fn hash(&self, hasher: &mut StateHasher) {
+        hasher.write_vec2(self.pos);
+        hasher.write_f64(self.direction);
+        hasher.write_f64(self.spread);
+        hasher.write_f64(self.speed.0);
+        hasher.write_f64(self.speed.1);
+        hasher.write_f64(self.radius.0);
+        hasher.write_f64(self.radius.1);
+        hasher.write_f64(self.rate);
+        hasher.write_usize(self.cap);
+        hasher.write_f64(self.due);
+        hasher.write_u64(self.seed);
+        hasher.write_u64(self.draws);
+        hasher.write_u8(self.enabled as u8);
+        hasher.write_u8(self.placed as u8);
+        hasher.write_usize(self.emitted.len());
+        for &id in &self.emitted {
+            hasher.write_u64(id);
+        }
+    }
+}
+
+impl Simulation {
+    // Tops every enabled emitter up with whatever this step owes, within its cap.
+    pub(super) fn run_emitters(&mut self, dt: f64) {
+        if self.emitters.is_empty() {
+            return;
+        }
+        optick::event!("Simulation::run_emitters");
+
+        let live: HashSet<u64> = self.particles.id().iter().copied().collect();
+        let units = self.units;
+        let density = units.density_from_si(EARTH_DENSITY);
+        let mut spawned = Vec::new();
+        for emitter in &mut self.emitters {
+            emitter.emitted.retain(|id| live.contains(id));
+            if !emitter.enabled {
+                emitter.due = 0.0;
+                continue;
+            }
+
+            emitter.due += emitter.rate * dt;
+            while emitter.due >= 1.0 {
+                emitter.due -= 1.0;
+                // owed particles aren't banked while full, or it would burst when one dies.
+                if emitter.live() >= emitter.cap {
+                    continue;
+                }
+                let angle =
+                    emitter.direction + emitter.rng.gen_range(-emitter.spread..=emitter.spread);
+                let speed = emitter.rng.gen_range(emitter.speed.0..=emitter.speed.1);
+                let radius = emitter.rng.gen_range(emitter.radius.0..=emitter.radius.1);
+                let speed = units.speed_from_si(DEFAULT_UNITS.speed_to_si(speed));
+                let radius = units.distance_from_si(DEFAULT_UNITS.distance_to_si(radius));
+                emitter.draws += 3;
+                let vel = vec2(angle.cos(), angle.sin()) * speed;
+                spawned.push(create_particle(emitter.pos, vel, radius, density));
+                emitter
+                    .emitted
+                    .push(self.next_id + spawned.len() as u64 - 1);
+            }
+        }
+
+        if !spawned.is_empty() {
+            for particle in spawned {
+                self.add_particle(particle);
+            }
+            self.diagnostics.reset();
+        }
+    }
+
+    // Writes the placed emitters to path, one per line. A scenario's own come back with it.
+    pub fn save_emitters(&self, path: impl AsRef<Path>) -> io::Result<usize> {
+        let placed: Vec<String> = self
+            .emitters
+            .iter()
+            .filter(|emitter| emitter.placed)
+            .map(Emitter::to_line)
+            .collect();
+        fs::write(path, placed.join("\n"))?;
+        Ok(placed.len())
+    }
+
+    // Replaces the placed emitters with those saved at path, leaving them as they were on error.
+    pub fn load_emitters(&mut self, path: impl AsRef<Path>) -> io::Result<usize> {
+        let loaded = fs::read_to_string(path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                Emitter::from_line(line).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("bad emitter: {line}"))
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        self.emitters.retain(|emitter| !emitter.placed);
+        for mut emitter in loaded {
+            emitter.placed = true;
+            self.emitters.push(emitter);
+        }
+        Ok(self
+            .emitters
+            .iter()
+            .filter(|emitter| emitter.placed)
+            .count())
+    }
+
+    // Index of the emitter closest to pos, if any is within max_distance.
+    pub(super) fn emitter_near(
+        &self,
+        pos: Vec2<f64, WorldSpace>,
+        max_distance: f64,
+    ) -> Option<usize> {
+        self.emitters
+            .iter()
+            .enumerate()
+            .map(|(i, emitter)| (i, (emitter.pos - pos).length()))
+            .filter(|&(_, distance)| distance <= max_distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+}
+
+impl GravitySim {
+    // Arrow along the middle of the cone, grey while disabled.
+    pub fn render_emitters(&mut self) {
+        let camera = self.camera;
+        let arrows: Vec<_> = self
+            .simulation
+            .emitters
+            .iter()
+            .map(|emitter| {
+                let tip = emitter.pos
+                    + vec2(emitter.direction.cos(), emitter.direction.sin())
+                        * (EMITTER_ARROW_PX / camera.zoom);
+                let colour = if emitter.enabled { GREEN } else { GRAY };
+                (camera.to_pixel(emitter.pos), camera.to_pixel(tip), colour)
+            })
+            .collect();
+        for (start, end, colour) in arrows {
+            Shape::draw_arrow(start, end, |x, y| self.plot_clipped(vec2(x, y), colour));
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use super::{particles::for_each_mut, GravitySim, ParticleSlicesMut, Simulation};
+use crate::utils::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldKind {
+    Gravity, // uniform along +y, down the screen unless the camera is rotating.
+    Attractor { centre: Vec2<f64, WorldSpace> }, // towards centre, negative strength repels.
+    Vortex { centre: Vec2<f64, WorldSpace> }, // around centre, positive turns from +x towards +y.
+    Drag { quadratic: bool }, // against the velocity, |v| or |v|^2.
+}
+
+// How a point field weakens with distance, relative to its strength at `range`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Falloff {
+    Constant,
+    Linear,        // 1 / r
+    InverseSquare, // 1 / r^2
+}
+
+impl Falloff {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Constant => Self::Linear,
+            Self::Linear => Self::InverseSquare,
+            Self::InverseSquare => Self::Constant,
+        }
+    }
+
+    // clamped to FIELD_CORE_RADIUS, so nothing is flung out of the middle.
+    fn scale(self, distance: f64, range: f64) -> f64 {
+        let ratio = range / distance.max(FIELD_CORE_RADIUS);
+        match self {
+            Self::Constant => 1.0,
+            Self::Linear => ratio,
+            Self::InverseSquare => ratio * ratio,
+        }
+    }
+
+    // Integral of scale from FIELD_CORE_RADIUS out to distance, so an attractor's potential is
+    // zero on the edge of its core.
+    fn potential(self, distance: f64, range: f64) -> f64 {
+        let core = FIELD_CORE_RADIUS;
+        if distance < core {
+            return self.scale(core, range) * (distance - core);
+        }
+        match self {
+            Self::Constant => distance - core,
+            Self::Linear => range * (distance / core).ln(),
+            Self::InverseSquare => range * range * (1.0 / core - 1.0 / distance),
+        }
+    }
+}
+
+// Environmental force acting on every particle, gravity-like fields are accelerations so
+// every particle responds alike whatever its mass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForceField {
+    pub kind: FieldKind,
+    pub strength: f64, // acceleration for gravity, attractors & vortices, 1 / time for drag.
+    pub falloff: Falloff,
+    pub range: f64, // distance at which a point field has its full strength.
+    pub enabled: bool,
+    pub follows_cursor: bool, // point fields only.
+}
+
+impl ForceField {
+    pub fn new(kind: FieldKind, strength: f64) -> Self {
+        Self {
+            kind,
+            strength,
+            falloff: Falloff::InverseSquare,
+            range: FIELD_RANGE,
+            enabled: false,
+            follows_cursor: true,
+        }
+    }
+
+    // The default sandbox set, toggled with F1..F4 in this order.
+    pub fn defaults() -> Vec<Self> {
+        let origin = vec2(0.0, 0.0);
+        vec![
+            Self::new(FieldKind::Gravity, FIELD_GRAVITY),
+            Self::new(FieldKind::Attractor { centre: origin }, FIELD_ATTRACTION),
+            Self::new(FieldKind::Vortex { centre: origin }, FIELD_SWIRL),
+            Self::new(FieldKind::Drag { quadratic: false }, FIELD_DRAG),
+        ]
+    }
+
+    pub fn centre(&self) -> Option<Vec2<f64, WorldSpace>> {
+        match self.kind {
+            FieldKind::Attractor { centre } | FieldKind::Vortex { centre } => Some(centre),
+            FieldKind::Gravity | FieldKind::Drag { .. } => None,
+        }
+    }
+
+    pub fn set_centre(&mut self, pos: Vec2<f64, WorldSpace>) {
+        if let FieldKind::Attractor { centre } | FieldKind::Vortex { centre } = &mut self.kind {
+            *centre = pos;
+        }
+    }
+
+    pub(super) fn acceleration(
+        &self,
+        pos: Vec2<f64, WorldSpace>,
+        vel: Vec2<f64, WorldSpace>,
+    ) -> Vec2<f64, WorldSpace> {
+        match self.kind {
+            FieldKind::Gravity => vec2(0.0, self.strength),
+            FieldKind::Attractor { centre } | FieldKind::Vortex { centre } => {
+                let dist = centre - pos;
+                let distance = dist.length();
+                // sitting exactly on the centre, no direction to push in.
+                if distance == 0.0 {
+                    return vec2(0.0, 0.0);
+                }
+                let magnitude = self.strength * self.falloff.scale(distance, self.range);
+                let normal = dist / distance;
+                match self.kind {
+                    FieldKind::Vortex { .. } => vec2(normal.y, -normal.x) * magnitude,
+                    _ => normal * magnitude,
+                }
+            }
+            FieldKind::Drag { quadratic } => {
+                let speed = if quadratic { vel.length() } else { 1.0 };
+                vel * (-self.strength * speed)
+            }
+        }
+    }
+
+    // Per unit mass. None for vortices, drag & point fields following the cursor, they all do
+    // work no potential accounts for.
+    fn potential(&self, pos: Vec2<f64, WorldSpace>) -> Option<f64> {
+        match self.kind {
+            FieldKind::Gravity => Some(-self.strength * pos.y),
+            FieldKind::Attractor { centre } if !self.follows_cursor => {
+                let distance = (centre - pos).length();
+                Some(self.strength * self.falloff.potential(distance, self.range))
+            }
+            FieldKind::Attractor { .. } | FieldKind::Vortex { .. } | FieldKind::Drag { .. } => None,
+        }
+    }
+}
+
+impl Simulation {
+    // Potential energy of every particle in the enabled fields, None if any of them isn't
+    // conservative.
+    pub(super) fn field_potential(&self) -> Option<f64> {
+        let mut potential = 0.0;
+        for field in self.force_fields.iter().filter(|field| field.enabled) {
+            for (&pos, &mass) in self.particles.pos().iter().zip(self.particles.mass()) {
+                potential += field.potential(pos)? * mass;
+            }
+        }
+        Some(potential)
+    }
+
+    pub(super) fn update_forces_external(&mut self) {
+        if !self.force_fields.iter().any(|field| field.enabled) {
+            return;
+        }
+        optick::event!("Physics Update - Force Fields");
+
+        let fields: Vec<ForceField> = self
+            .force_fields
+            .iter()
+            .filter(|field| field.enabled)
+            .copied()
+            .collect();
+        let parallel = self.use_parallel();
+        let ParticleSlicesMut {
+            pos,
+            vel,
+            force,
+            mass,
+            ..
+        } = self.particles.slices_mut();
+        let (pos, vel, mass): (&[_], &[_], &[_]) = (pos, vel, mass);
+        for_each_mut(parallel, force, |i, force| {
+            for field in &fields {
+                *force += field.acceleration(pos[i], vel[i]) * mass[i];
+            }
+        });
+    }
+}
+
+impl GravitySim {
+    // Ring at the full strength range of every enabled point field.
+    pub fn render_force_fields(&mut self) {
+        let fields: Vec<ForceField> = self.simulation.force_fields.clone();
+        for field in fields.iter().filter(|field| field.enabled) {
+            let Some(centre) = field.centre() else {
+                continue;
+            };
+            let centre = self.camera.to_pixel(centre);
+            let radius = (field.range * self.camera.zoom) as i32;
+            Shape::CircleOutline.draw(radius, |off_x, off_y| {
+                self.plot_clipped(vec2(centre.x + off_x, centre.y + off_y), GRAY)
+            });
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use super::{
+    gravity_between, gravity_potential, integrator::Integrator, particles::for_each_mut,
+    softening::Gravity, ParticleSlices, ParticleSlicesMut, Simulation,
+};
+use crate::utils::*;
+
+// Which forces act on the particles, each one switched on & off on its own.
+// Charge is measured in mass units with Coulomb's constant equal to G, so two bodies
+// carrying charge == mass repel exactly as hard as they attract.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForceModel {
+    pub gravity: bool,
+    pub electrostatics: bool, // Coulomb between every pair of charged particles.
+    pub electric_field: Vec2<f64, WorldSpace>, // uniform, force per unit charge.
+    pub magnetic_field: f64,  // uniform & out of the screen, q/m * B is the cyclotron frequency.
+}
+
+impl ForceModel {
+    pub fn new() -> Self {
+        Self {
+            gravity: true,
+            electrostatics: true,
+            electric_field: vec2(0.0, 0.0),
+            magnetic_field: 0.0,
+        }
+    }
+
+    // Potential energy of the pair i, j from every conservative force that's on. Like charges
+    // repel, the negated gravitational potential with charges in place of masses.
+    pub(super) fn pair_potential(
+        &self,
+        particles: &ParticleSlices,
+        i: usize,
+        j: usize,
+        gravity: Gravity,
+    ) -> f64 {
+        let ParticleSlices {
+            pos,
+            mass,
+            radius,
+            charge,
+            ..
+        } = particles;
+        let contact = radius[i] + radius[j];
+        let mut potential = 0.0;
+        if self.gravity {
+            potential += gravity_potential(pos[i], mass[i], pos[j], mass[j], contact, gravity);
+        }
+        if self.electrostatics {
+            potential -= gravity_potential(pos[i], charge[i], pos[j], charge[j], contact, gravity);
+        }
+        potential
+    }
+
+    // Potential energy of a charge in the uniform electric field, the magnetic field does no work.
+    pub(super) fn field_potential(&self, pos: Vec2<f64, WorldSpace>, charge: f64) -> f64 {
+        -charge * self.electric_field.dot(pos)
+    }
+}
+
+impl Simulation {
+    // Always a direct sum, charges of both signs cancel inside Barnes-Hut cells so a monopole
+    // tree would be wrong. Only charged particles take part, so the cost is O(charged^2).
+    pub(super) fn update_forces_electrostatic(&mut self) {
+        optick::event!("Physics Update - Electrostatics");
+
+        // same separation, softening & contact cut off as gravity, only the sign differs.
+        let gravity = self.gravity();
+        let parallel = self.use_parallel();
+        let ParticleSlicesMut {
+            pos,
+            force,
+            radius,
+            charge,
+            ..
+        } = self.particles.slices_mut();
+        let (pos, radius, charge): (&[_], &[_], &[_]) = (pos, radius, charge);
+        let charged: Vec<usize> = (0..pos.len()).filter(|&i| charge[i] != 0.0).collect();
+
+        // each particle sums its own pushes in index order, so parallel is bit-identical.
+        for_each_mut(parallel, force, |i, force| {
+            if charge[i] == 0.0 {
+                return;
+            }
+            for &j in charged.iter().filter(|&&j| j != i) {
+                *force -= gravity_between(
+                    pos[i],
+                    charge[i],
+                    pos[j],
+                    charge[j],
+                    radius[i] + radius[j],
+                    gravity,
+                );
+            }
+        });
+    }
+
+    // q (E + v x B), with B along z, v x B = (v.y B, -v.x B). Only RK4 sees the magnetic part
+    // as a force, an explicit kick with it grows |v| every step, so the other integrators
+    // rotate the velocity exactly instead (see kick).
+    pub(super) fn update_forces_fields(&mut self) {
+        let ForceModel {
+            electric_field,
+            magnetic_field,
+            ..
+        } = self.forces;
+        let magnetic_field = match self.integrator {
+            Integrator::Rk4 => magnetic_field,
+            _ => 0.0,
+        };
+        if electric_field == vec2(0.0, 0.0) && magnetic_field == 0.0 {
+            return;
+        }
+
+        let parallel = self.use_parallel();
+        let ParticleSlicesMut {
+            vel, force, charge, ..
+        } = self.particles.slices_mut();
+        let (vel, charge): (&[_], &[_]) = (vel, charge);
+        for_each_mut(parallel, force, |i, force| {
+            let lorentz = vec2(vel[i].y, -vel[i].x) * magnetic_field;
+            *force += (electric_field + lorentz) * charge[i];
+        });
+    }
+}
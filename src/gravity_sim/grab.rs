@@ -0,0 +1,107 @@
+use super::{
+    motion::{Motion, Path},
+    GravitySim,
+};
+use crate::{app::InputData, utils::*};
+use log::info;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// A particle picked up with the mouse, kinematic until it's let go.
+#[derive(Debug, Clone)]
+pub struct Grab {
+    pub id: u64,
+    offset: Vec2<f64, WorldSpace>, // particle - cursor at pickup, so it doesn't snap to the cursor.
+    motion: Motion,                // restored on release.
+    picked_up: Instant,            // wall clock origin of the sample times.
+    samples: VecDeque<(f64, Vec2<f64, WorldSpace>)>, // recent (world time, cursor), oldest first.
+}
+
+impl Grab {
+    // Cursor velocity over the last GRAB_THROW_WINDOW_MS, in world units per world time unit,
+    // SIM_TIME_SCALE of which pass per real second.
+    fn velocity(&self) -> Vec2<f64, WorldSpace> {
+        let (Some(&(start, from)), Some(&(end, to))) = (self.samples.front(), self.samples.back())
+        else {
+            return vec2(0.0, 0.0);
+        };
+        let elapsed = end - start;
+        if elapsed > 0.0 {
+            (to - from) / elapsed
+        } else {
+            vec2(0.0, 0.0)
+        }
+    }
+}
+
+impl GravitySim {
+    // Picks up, carries & throws particles. True while the mouse belongs to a grab (including the
+    // release), so the same press doesn't also spawn a particle.
+    pub fn update_grab(&mut self, inputs: &InputData) -> bool {
+        optick::event!("GravitySim::update_grab");
+
+        if self.grab.is_none() && inputs.is_mouse_pressed() {
+            if let Some(id) = self.particle_under(inputs.mouse_pressed.pos) {
+                let i = self.simulation.index_of(id).unwrap();
+                let p = self.simulation.particles.get(i);
+                self.grab = Some(Grab {
+                    id,
+                    offset: p.pos - self.mouse_to_world(inputs.mouse_pressed.pos),
+                    motion: p.motion,
+                    picked_up: Instant::now(),
+                    samples: VecDeque::new(),
+                });
+            }
+        }
+        let Some(grab) = &mut self.grab else {
+            return false;
+        };
+
+        // a replay has to throw the same way it did live, so lockstep runs time the cursor in
+        // simulation steps rather than by the wall clock.
+        let now = if self.simulation.deterministic {
+            self.simulation.step as f64 * SIM_TIMESTEP.as_secs_f64() * SIM_TIME_SCALE
+        } else {
+            grab.picked_up.elapsed().as_secs_f64() * SIM_TIME_SCALE
+        };
+        let window = Duration::from_millis(GRAB_THROW_WINDOW_MS).as_secs_f64() * SIM_TIME_SCALE;
+        let cursor = self
+            .camera
+            .to_world(inputs.mouse_pos.scale(self.state.scale));
+        grab.samples.push_back((now, cursor));
+        while grab
+            .samples
+            .front()
+            .is_some_and(|&(time, _)| now - time > window)
+        {
+            grab.samples.pop_front();
+        }
+
+        let (id, vel, pos) = (grab.id, grab.velocity(), cursor + grab.offset);
+        // edits quietly fail if it was merged or culled out from under the cursor,
+        // the grab still owns the mouse until it's released.
+        if inputs.is_mouse_down() {
+            // kinematic, so the integrator carries it along at the cursor's velocity between
+            // frames without gravity pulling it off the cursor.
+            self.simulation.edit_particle(id, |p| {
+                p.pos = pos;
+                p.vel = vel;
+                p.motion = Motion::Kinematic(Path::Line);
+            });
+        } else {
+            // pinned & scripted bodies are just moved, their path takes the velocity over again.
+            let motion = grab.motion;
+            let thrown = self.simulation.edit_particle(id, |p| {
+                p.vel = vel;
+                p.motion = motion;
+            });
+            if thrown {
+                info!("Threw particle {id} at {vel:?}");
+            }
+            self.grab = None;
+        }
+        true
+    }
+}
@@ -0,0 +1,87 @@
+use super::{Particle, Simulation};
+use crate::utils::*;
+
+// Two body view of a particle against whatever pulls on it hardest.
+#[derive(Debug, Clone, Copy)]
+pub struct Orbit {
+    pub primary: u64, // id of the dominant body.
+    pub distance: f64,
+    pub relative_speed: f64,
+    pub specific_energy: f64, // per unit reduced mass, < 0.0 == bound.
+    pub semi_major_axis: Option<f64>, // bound orbits only.
+}
+
+// Everything the inspector shows about one particle.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Inspection {
+    pub particle: Particle,
+    pub speed: f64,
+    pub orbit: Option<Orbit>, // None when it's the only particle.
+}
+
+impl Simulation {
+    pub fn index_of(&self, id: u64) -> Option<usize> {
+        self.particles.id().iter().position(|&other| other == id)
+    }
+
+    pub(super) fn inspect(&self, id: u64) -> Option<Inspection> {
+        let particle = self.particles.get(self.index_of(id)?);
+        let gravity = self.gravity();
+
+        // largest G * m / r^2, the body the particle is actually orbiting (softening aside).
+        let primary = self
+            .particles
+            .rows()
+            .filter(|other| other.id != id && other.mass > 0.0)
+            .max_by(|a, b| {
+                let pull = |other: &Particle| {
+                    let r = gravity.separation(particle.pos, other.pos).length();
+                    other.mass * gravity.force_kernel(r)
+                };
+                pull(a).total_cmp(&pull(b))
+            });
+
+        let orbit = primary.map(|primary| {
+            let distance = gravity.separation(particle.pos, primary.pos).length();
+            let relative_vel = particle.vel - primary.vel;
+            let mu = gravity.grav_const * (particle.mass + primary.mass);
+            let specific_energy =
+                0.5 * relative_vel.dot(relative_vel) - mu * gravity.potential_kernel(distance);
+            Orbit {
+                primary: primary.id,
+                distance,
+                relative_speed: relative_vel.length(),
+                specific_energy,
+                semi_major_axis: (specific_energy < 0.0).then(|| -mu / (2.0 * specific_energy)),
+            }
+        });
+
+        Some(Inspection {
+            particle,
+            speed: particle.vel.length(),
+            orbit,
+        })
+    }
+
+    // Applies edit to the particle with this id, false if it no longer exists.
+    pub(super) fn edit_particle(&mut self, id: u64, edit: impl FnOnce(&mut Particle)) -> bool {
+        let Some(i) = self.index_of(id) else {
+            return false;
+        };
+        let mut particle = self.particles.get(i);
+        edit(&mut particle);
+        self.particles.set(i, particle);
+        self.diagnostics.reset();
+        self.acc_key = None;
+        true
+    }
+
+    pub fn remove_particle(&mut self, id: u64) -> bool {
+        let Some(i) = self.index_of(id) else {
+            return false;
+        };
+        self.particles.remove(i);
+        self.diagnostics.reset();
+        true
+    }
+}
@@ -0,0 +1,252 @@
+use super::{
+    boundary::Boundary, force_fields::ForceField, forces::ForceModel, particles::for_each_mut,
+    softening::Softening, ForceSolver, ParticleSlices, ParticleSlicesMut, Simulation,
+};
+use crate::utils::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Integrator {
+    ExplicitEuler,     // 1st order, gains energy every step, here for comparison.
+    SemiImplicitEuler, // 1st order symplectic, velocity first then position.
+    Leapfrog,          // 2nd order symplectic, kick-drift-kick.
+    VelocityVerlet,    // 2nd order symplectic, same trajectory as KDK written in position form.
+    Rk4,               // 4th order, not symplectic so energy slowly drifts, 4 force evaluations.
+}
+
+impl Integrator {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::ExplicitEuler => Self::SemiImplicitEuler,
+            Self::SemiImplicitEuler => Self::Leapfrog,
+            Self::Leapfrog => Self::VelocityVerlet,
+            Self::VelocityVerlet => Self::Rk4,
+            Self::Rk4 => Self::ExplicitEuler,
+        }
+    }
+}
+
+// Everything the stored accelerations depend on besides the particles' own motion, when any of it
+// changes (or particles are added, removed or edited) they're stale & must be evaluated again.
+// ids only ever grow between clears so `next_id` catches a spawn even when a merge or cull in the
+// same step left the count unchanged, & any removal shows up in `count`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccelerationKey {
+    count: usize,
+    next_id: u64,
+    links: usize,
+    integrator: Integrator, // only RK4 sees the magnetic field as a force.
+    forces: ForceModel,
+    fields: Vec<ForceField>, // enabled ones only, disabled fields push nothing.
+    solver: ForceSolver,
+    theta: f64,
+    softening: Softening,
+    softening_length: f64,
+    boundary: Boundary,
+}
+
+impl Simulation {
+    fn acceleration_key(&self) -> AccelerationKey {
+        AccelerationKey {
+            count: self.particles.len(),
+            next_id: self.next_id,
+            links: self.constraints.links.len(),
+            integrator: self.integrator,
+            forces: self.forces,
+            fields: self
+                .force_fields
+                .iter()
+                .filter(|field| field.enabled)
+                .copied()
+                .collect(),
+            solver: self.solver,
+            theta: self.theta,
+            softening: self.softening,
+            softening_length: self.softening_length,
+            boundary: self.boundary,
+        }
+    }
+
+    // Advances every particle by dt (in simulation seconds) using the selected integrator,
+    // in several shorter steps while a close encounter is in progress.
+    pub fn integrate(&mut self, dt: f64) {
+        optick::event!("Simulation::integrate");
+
+        let substeps = self.encounter_substeps(dt);
+        for _ in 0..substeps {
+            self.integrate_step(dt / substeps as f64);
+        }
+    }
+
+    // Steps needed so no particle's acceleration changes faster than a step can follow,
+    // dt <= ENCOUNTER_ACCURACY * sqrt(length / |acc|) where length is its radius, or the softening
+    // length when that's larger. Judged on the accelerations from the previous step.
+    fn encounter_substeps(&self, dt: f64) -> usize {
+        if !self.substep_encounters {
+            return 1;
+        }
+        let min_length = match self.softening {
+            Softening::Off => 0.0,
+            _ => self.softening_length,
+        };
+        let ParticleSlices { acc, radius, .. } = self.particles.slices();
+        // a body with no acceleration has no timescale, it's left out rather than dividing by 0.
+        let shortest = acc
+            .iter()
+            .zip(radius)
+            .filter(|(acc, _)| acc.length() != 0.0)
+            .map(|(acc, &radius)| (radius.max(min_length) / acc.length()).sqrt())
+            .fold(f64::INFINITY, f64::min);
+        let substeps = (dt / (ENCOUNTER_ACCURACY * shortest)).ceil() as usize;
+        substeps.clamp(1, ENCOUNTER_MAX_SUBSTEPS)
+    }
+
+    // Leapfrog & velocity verlet reuse `acc` from the end of the previous step,
+    // so they cost a single force evaluation per step unless it has gone stale.
+    fn integrate_step(&mut self, dt: f64) {
+        let reuses_acc = matches!(
+            self.integrator,
+            Integrator::Leapfrog | Integrator::VelocityVerlet
+        );
+        if reuses_acc && self.acc_key.as_ref() != Some(&self.acceleration_key()) {
+            self.update_accelerations();
+        }
+
+        let scripted = self.scripted_positions();
+        let integrator = match self.integrator {
+            // the magnetic rotation needs a kick either side of the drift, KDK is the same trajectory.
+            Integrator::VelocityVerlet if self.forces.magnetic_field != 0.0 => Integrator::Leapfrog,
+            integrator => integrator,
+        };
+        match integrator {
+            Integrator::ExplicitEuler => {
+                self.update_accelerations();
+                self.drift(dt);
+                self.kick(dt);
+            }
+            Integrator::SemiImplicitEuler => {
+                self.update_accelerations();
+                self.kick(dt);
+                self.drift(dt);
+            }
+            Integrator::Leapfrog => {
+                self.kick(dt / 2.0);
+                self.drift(dt);
+                self.update_accelerations();
+                self.kick(dt / 2.0);
+            }
+            Integrator::VelocityVerlet => {
+                let parallel = self.use_parallel();
+                let prev_acc = self.particles.acc().to_vec();
+                let ParticleSlicesMut { pos, vel, acc, .. } = self.particles.slices_mut();
+                let (vel, acc): (&[_], &[_]) = (vel, acc);
+                for_each_mut(parallel, pos, |i, pos| {
+                    *pos += vel[i] * dt + acc[i] * (dt * dt / 2.0);
+                });
+
+                self.update_accelerations();
+                let ParticleSlicesMut { vel, acc, .. } = self.particles.slices_mut();
+                let acc: &[_] = acc;
+                for_each_mut(parallel, vel, |i, vel| {
+                    *vel += (prev_acc[i] + acc[i]) * (dt / 2.0);
+                });
+            }
+            Integrator::Rk4 => self.integrate_rk4(dt),
+        }
+        self.follow_paths(&scripted, dt);
+    }
+
+    fn integrate_rk4(&mut self, dt: f64) {
+        let parallel = self.use_parallel();
+        let start_pos = self.particles.pos().to_vec();
+        let start_vel = self.particles.vel().to_vec();
+        let zero = vec2(0.0, 0.0);
+        let mut k_pos = vec![zero; start_pos.len()];
+        let mut k_vel = vec![zero; start_pos.len()];
+        let mut sum_pos = vec![zero; start_pos.len()];
+        let mut sum_vel = vec![zero; start_pos.len()];
+
+        // (offset into the step, weight) for k1..k4, each stage is evaluated from the previous k.
+        for (offset, weight) in [(0.0, 1.0), (0.5, 2.0), (0.5, 2.0), (1.0, 1.0)] {
+            let ParticleSlicesMut { pos, vel, .. } = self.particles.slices_mut();
+            for_each_mut(parallel, pos, |i, pos| {
+                *pos = start_pos[i] + k_pos[i] * (offset * dt);
+            });
+            for_each_mut(parallel, vel, |i, vel| {
+                *vel = start_vel[i] + k_vel[i] * (offset * dt);
+            });
+
+            self.update_accelerations();
+            k_pos.copy_from_slice(self.particles.vel());
+            k_vel.copy_from_slice(self.particles.acc());
+            for i in 0..start_pos.len() {
+                sum_pos[i] += k_pos[i] * weight;
+                sum_vel[i] += k_vel[i] * weight;
+            }
+        }
+
+        let ParticleSlicesMut { pos, vel, .. } = self.particles.slices_mut();
+        for_each_mut(parallel, pos, |i, pos| {
+            *pos = start_pos[i] + sum_pos[i] * (dt / 6.0);
+        });
+        for_each_mut(parallel, vel, |i, vel| {
+            *vel = start_vel[i] + sum_vel[i] * (dt / 6.0);
+        });
+    }
+
+    // vel += acc * dt, with a magnetic field it's a Boris push: half the kick, an exact rotation
+    // by the cyclotron angle, then the other half, so B alone never changes |v|.
+    fn kick(&mut self, dt: f64) {
+        let parallel = self.use_parallel();
+        let magnetic_field = self.forces.magnetic_field;
+        let ParticleSlicesMut {
+            vel,
+            acc,
+            mass,
+            charge,
+            motion,
+            ..
+        } = self.particles.slices_mut();
+        let (acc, mass, charge, motion): (&[_], &[_], &[_], &[_]) = (acc, mass, charge, motion);
+        for_each_mut(parallel, vel, |i, vel| {
+            if magnetic_field == 0.0 || charge[i] == 0.0 || !motion[i].is_dynamic() {
+                *vel += acc[i] * dt;
+                return;
+            }
+            // positive q B turns from +x towards -y, see update_forces_fields.
+            let half = acc[i] * (dt / 2.0);
+            let angle = -charge[i] / mass[i] * magnetic_field * dt;
+            *vel = (*vel + half).rotate(angle) + half;
+        });
+    }
+
+    // pos += vel * dt
+    fn drift(&mut self, dt: f64) {
+        let parallel = self.use_parallel();
+        let ParticleSlicesMut { pos, vel, .. } = self.particles.slices_mut();
+        let vel: &[_] = vel;
+        for_each_mut(parallel, pos, |i, pos| *pos += vel[i] * dt);
+    }
+
+    // Re-evaluates forces at the current positions and stores a = F/m on each particle.
+    fn update_accelerations(&mut self) {
+        self.update_forces();
+        let parallel = self.use_parallel();
+        let ParticleSlicesMut {
+            acc,
+            force,
+            mass,
+            motion,
+            ..
+        } = self.particles.slices_mut();
+        let (force, mass, motion): (&[_], &[_], &[_]) = (force, mass, motion);
+        // scripted bodies are put back on their paths after the step, forces never move them.
+        for_each_mut(parallel, acc, |i, acc| {
+            *acc = if motion[i].is_dynamic() {
+                force[i] / mass[i]
+            } else {
+                vec2(0.0, 0.0)
+            };
+        });
+        self.acc_key = Some(self.acceleration_key());
+    }
+}
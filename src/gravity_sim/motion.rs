@@ -0,0 +1,129 @@
+use super::{Particle, ParticleSlicesMut, Simulation};
+use crate::utils::*;
+use std::hash::Hasher;
+
+// How a particle moves, scripted bodies still pull on & collide with everything else.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Motion {
+    Dynamic,         // integrated from the forces on it.
+    Static,          // pinned where it is, e.g. a fixed star.
+    Kinematic(Path), // follows the path whatever pushes on it.
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Path {
+    Line, // straight on at its own velocity.
+    Circle {
+        centre: Vec2<f64, WorldSpace>,
+        angular_vel: f64, // radians per world time unit, positive turns from +x towards +y.
+    },
+}
+
+impl Motion {
+    pub const fn is_dynamic(self) -> bool {
+        matches!(self, Self::Dynamic)
+    }
+
+    // Circle about centre through pos, turning at whatever rate vel gives it around centre.
+    pub fn orbit(
+        centre: Vec2<f64, WorldSpace>,
+        pos: Vec2<f64, WorldSpace>,
+        vel: Vec2<f64, WorldSpace>,
+    ) -> Self {
+        let arm = pos - centre;
+        let radius_sq = arm.dot(arm);
+        let angular_vel = if radius_sq > 0.0 {
+            (arm.x * vel.y - arm.y * vel.x) / radius_sq
+        } else {
+            0.0
+        };
+        Self::Kinematic(Path::Circle {
+            centre,
+            angular_vel,
+        })
+    }
+
+    pub fn hash(self, hasher: &mut StateHasher) {
+        match self {
+            Self::Dynamic => hasher.write_u8(0),
+            Self::Static => hasher.write_u8(1),
+            Self::Kinematic(Path::Line) => hasher.write_u8(2),
+            Self::Kinematic(Path::Circle {
+                centre,
+                angular_vel,
+            }) => {
+                hasher.write_u8(3);
+                hasher.write_vec2(centre);
+                hasher.write_f64(angular_vel);
+            }
+        }
+    }
+}
+
+// What the spawn tool gives new particles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnMotion {
+    Dynamic,
+    Static, // drag velocity is ignored.
+    Line,   // carries on at the drag velocity.
+    Orbit,  // circles the selected particle (or the view centre) at the drag velocity.
+}
+
+impl SpawnMotion {
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Dynamic => Self::Static,
+            Self::Static => Self::Line,
+            Self::Line => Self::Orbit,
+            Self::Orbit => Self::Dynamic,
+        }
+    }
+}
+
+impl Particle {
+    // Scripted bodies can't be pushed, they act like infinite mass in collisions.
+    pub(super) fn inverse_mass(&self) -> f64 {
+        if self.motion.is_dynamic() {
+            1.0 / self.mass
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Simulation {
+    // Positions of every scripted body, taken before the integrator moves anything.
+    pub(super) fn scripted_positions(&self) -> Vec<(usize, Vec2<f64, WorldSpace>)> {
+        let (motion, pos) = (self.particles.motion(), self.particles.pos());
+        (0..motion.len())
+            .filter(|&i| !motion[i].is_dynamic())
+            .map(|i| (i, pos[i]))
+            .collect()
+    }
+
+    // Overwrites whatever the integrator did to scripted bodies with where their path puts them.
+    pub(super) fn follow_paths(&mut self, start: &[(usize, Vec2<f64, WorldSpace>)], dt: f64) {
+        let ParticleSlicesMut {
+            motion, pos, vel, ..
+        } = self.particles.slices_mut();
+        for &(i, from) in start {
+            match motion[i] {
+                Motion::Dynamic => {}
+                Motion::Static => {
+                    pos[i] = from;
+                    vel[i] = vec2(0.0, 0.0);
+                }
+                Motion::Kinematic(Path::Line) => pos[i] = from + vel[i] * dt,
+                Motion::Kinematic(Path::Circle {
+                    centre,
+                    angular_vel,
+                }) => {
+                    // relative to the start, so dragging it off the circle just changes the radius.
+                    let arm = (from - centre).rotate(angular_vel * dt);
+                    pos[i] = centre + arm;
+                    vel[i] = vec2(-arm.y, arm.x) * angular_vel;
+                }
+            }
+        }
+    }
+}
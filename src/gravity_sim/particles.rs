@@ -0,0 +1,106 @@
+use super::Particle;
+use rayon::prelude::*;
+use soa_rs::Soa;
+use std::ops::{Deref, DerefMut};
+
+// Structure-of-arrays particle store, each field is its own contiguous column.
+// Hot loops work on the columns directly (`pos()`, `slices_mut()`, ...),
+// whole particles are copied in & out with get/set.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Particles(Soa<Particle>);
+
+impl Particles {
+    pub fn new() -> Self {
+        Self(Soa::new())
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(Soa::with_capacity(capacity))
+    }
+
+    pub fn get(&self, index: usize) -> Particle {
+        let p = self.0.idx(index);
+        Particle {
+            id: *p.id,
+            pos: *p.pos,
+            prev_pos: *p.prev_pos,
+            vel: *p.vel,
+            acc: *p.acc,
+            force: *p.force,
+            mass: *p.mass,
+            radius: *p.radius,
+            collisions: *p.collisions,
+            charge: *p.charge,
+            motion: *p.motion,
+        }
+    }
+
+    pub fn set(&mut self, index: usize, particle: Particle) {
+        let p = self.0.idx_mut(index);
+        *p.id = particle.id;
+        *p.pos = particle.pos;
+        *p.prev_pos = particle.prev_pos;
+        *p.vel = particle.vel;
+        *p.acc = particle.acc;
+        *p.force = particle.force;
+        *p.mass = particle.mass;
+        *p.radius = particle.radius;
+        *p.collisions = particle.collisions;
+        *p.charge = particle.charge;
+        *p.motion = particle.motion;
+    }
+
+    // Copies of every particle, in order.
+    pub fn rows(&self) -> impl Iterator<Item = Particle> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    // Removes every particle keep returns false for, preserving the order of the rest.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Particle) -> bool) {
+        let mut kept = 0;
+        for i in 0..self.len() {
+            let p = self.get(i);
+            if keep(&p) {
+                if kept != i {
+                    self.set(kept, p);
+                }
+                kept += 1;
+            }
+        }
+        self.0.truncate(kept);
+    }
+}
+
+impl Deref for Particles {
+    type Target = Soa<Particle>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Particles {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl FromIterator<Particle> for Particles {
+    fn from_iter<I: IntoIterator<Item = Particle>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+// Runs f(index, element) over a single column, on the rayon pool when parallel.
+// Other columns can be read by index, each call only writes its own element.
+pub fn for_each_mut<T: Send>(
+    parallel: bool,
+    column: &mut [T],
+    f: impl Fn(usize, &mut T) + Sync + Send,
+) {
+    if parallel {
+        column.par_iter_mut().enumerate().for_each(|(i, x)| f(i, x));
+    } else {
+        column.iter_mut().enumerate().for_each(|(i, x)| f(i, x));
+    }
+}
@@ -0,0 +1,261 @@
+use super::{
+    boundary::Boundary, create_particle, force_fields::ForceField, gravity_between,
+    softening::Gravity, Simulation,
+};
+use crate::utils::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PredictionEnd {
+    Running,                          // ran out of steps.
+    Collision(Vec2<f64, WorldSpace>), // touched another particle here.
+    Escape(Vec2<f64, WorldSpace>),    // unbound & moving away from the centre of mass here.
+}
+
+// Where a particle would go if it were launched right now.
+#[derive(Debug, Clone)]
+pub struct Prediction {
+    pub path: Vec<Vec2<f64, WorldSpace>>, // one position per step, starting at the launch point.
+    pub end: PredictionEnd,
+}
+
+// Every other body after each step, recorded while a launch is being aimed. The probe is light,
+// so the rest of the system doesn't notice it & only the probe is integrated each frame.
+#[derive(Debug, Clone)]
+pub struct Background {
+    sim: Simulation,   // a copy, stepped on to the last frame.
+    start_step: usize, // step of the real simulation frames[0] matches.
+    frames: Vec<Frame>,
+    credit: usize, // pair interactions banked towards the next step.
+}
+
+#[derive(Debug, Clone)]
+struct Frame {
+    pos: Vec<Vec2<f64, WorldSpace>>,
+    mass: Vec<f64>,
+    radius: Vec<f64>,
+    charge: Vec<f64>,
+}
+
+impl Frame {
+    fn record(sim: &Simulation) -> Self {
+        Self {
+            pos: sim.particles.pos().to_vec(),
+            mass: sim.particles.mass().to_vec(),
+            radius: sim.particles.radius().to_vec(),
+            charge: sim.particles.charge().to_vec(),
+        }
+    }
+
+    // Positive specific orbital energy against every body, heading outward.
+    fn is_escaping(
+        &self,
+        pos: Vec2<f64, WorldSpace>,
+        vel: Vec2<f64, WorldSpace>,
+        grav_const: f64,
+    ) -> bool {
+        let mut potential = 0.0;
+        let mut mass = 0.0;
+        let mut centre_of_mass = vec2(0.0, 0.0);
+        for (&other_pos, &other_mass) in self.pos.iter().zip(&self.mass) {
+            potential -= grav_const * other_mass / (other_pos - pos).length();
+            mass += other_mass;
+            centre_of_mass += other_pos * other_mass;
+        }
+        if mass == 0.0 {
+            return false; // nothing to escape from, it's just drifting.
+        }
+        centre_of_mass /= mass;
+
+        let energy = 0.5 * vel.dot(vel) + potential;
+        energy > 0.0 && (pos - centre_of_mass).dot(vel) > 0.0
+    }
+}
+
+// The would-be particle, only ever pushed on by the recorded bodies & the fields.
+#[derive(Debug, Clone, Copy)]
+struct Probe {
+    pos: Vec2<f64, WorldSpace>,
+    vel: Vec2<f64, WorldSpace>,
+    radius: f64,
+    mass: f64,
+    charge: f64,
+}
+
+impl Background {
+    // Frames from the real simulation's current step on, extended by at most a pair budget's
+    // worth of steps per call so aiming at a big system never stalls a frame. Unspent budget
+    // carries over, so a system too big for one step per frame still records one every few.
+    fn frames(&mut self, real: &Simulation, steps: usize) -> &[Frame] {
+        let offset = real.step - self.start_step;
+        self.frames.drain(..offset.min(self.frames.len()));
+        self.start_step = real.step;
+
+        let count = self.sim.particles.len().max(1);
+        let cost = count * count;
+        let dt = SIM_TIMESTEP.as_secs_f64() * SIM_TIME_SCALE;
+        self.credit = (self.credit + PREDICTION_PAIR_BUDGET).min(PREDICTION_PAIR_BUDGET.max(cost));
+        while self.frames.len() <= steps && self.credit >= cost {
+            self.credit -= cost;
+            self.sim.advance(dt);
+            self.frames.push(Frame::record(&self.sim));
+        }
+        &self.frames[..self.frames.len().min(steps + 1)]
+    }
+
+    // Stale once the real simulation has gone back, or lost or gained bodies the copy didn't.
+    fn matches(&self, real: &Simulation) -> bool {
+        real.step >= self.start_step
+            && self
+                .frames
+                .get(real.step - self.start_step)
+                .is_some_and(|frame| frame.pos.len() == real.particles.len())
+    }
+}
+
+impl Simulation {
+    // Forward integrates a launch from pos through the recorded background, the real
+    // simulation is left untouched. The background is (re)recorded when it's missing or stale.
+    pub fn predict_trajectory(
+        &self,
+        background: &mut Option<Background>,
+        pos: Vec2<f64, WorldSpace>,
+        vel: Vec2<f64, WorldSpace>,
+        radius: f64,
+        charge_per_mass: f64,
+        steps: usize,
+    ) -> Prediction {
+        optick::event!("Simulation::predict_trajectory");
+
+        if !background.as_ref().is_some_and(|bg| bg.matches(self)) {
+            let mut sim = self.clone();
+            sim.diagnostics.enabled = false;
+            // rebased on the current step, a big system the real run outpaces keeps the budget
+            // it banked, or it would start from nothing every frame & never record a step.
+            let credit = background.as_ref().map_or(0, |bg| bg.credit);
+            *background = Some(Background {
+                frames: vec![Frame::record(&sim)],
+                sim,
+                start_step: self.step,
+                credit,
+            });
+        }
+        let frames = background.as_mut().unwrap().frames(self, steps);
+
+        let density = self.units.density_from_si(EARTH_DENSITY);
+        let particle = create_particle(pos, vel, radius, density);
+        let mut probe = Probe {
+            pos,
+            vel,
+            radius,
+            mass: particle.mass,
+            charge: charge_per_mass * particle.mass,
+        };
+
+        let dt = SIM_TIMESTEP.as_secs_f64() * SIM_TIME_SCALE;
+        let gravity = self.gravity();
+        let fields: Vec<ForceField> = self
+            .force_fields
+            .iter()
+            .filter(|field| field.enabled)
+            .copied()
+            .collect();
+        let mut path = Vec::with_capacity(frames.len());
+        path.push(pos);
+
+        // kick-drift-kick, with the same Boris rotation as the real kick.
+        let mut acc = self.probe_acceleration(&frames[0], &probe, &fields, gravity);
+        for frame in &frames[1..] {
+            probe.vel = self.probe_kick(&probe, acc, dt / 2.0);
+            probe.pos += probe.vel * dt;
+            match self.boundary {
+                Boundary::Open => {}
+                Boundary::Reflective => self.bounds.reflect(
+                    &mut probe.pos,
+                    &mut probe.vel,
+                    probe.radius,
+                    self.restitution,
+                ),
+                Boundary::Periodic => probe.pos = self.bounds.wrap(probe.pos),
+            }
+            acc = self.probe_acceleration(frame, &probe, &fields, gravity);
+            probe.vel = self.probe_kick(&probe, acc, dt / 2.0);
+            path.push(probe.pos);
+
+            let touching = (0..frame.pos.len()).any(|j| {
+                gravity.separation(probe.pos, frame.pos[j]).length()
+                    < frame.radius[j] + probe.radius
+                    && frame.mass[j] != 0.0
+            });
+            if touching {
+                return Prediction {
+                    end: PredictionEnd::Collision(probe.pos),
+                    path,
+                };
+            }
+            // walls & wrapping keep everything in, only open space can be escaped.
+            let escaped = self.boundary == Boundary::Open
+                && ((probe.pos - self.bounds.centre()).length() > self.escape_radius
+                    || frame.is_escaping(probe.pos, probe.vel, gravity.grav_const));
+            if escaped {
+                return Prediction {
+                    end: PredictionEnd::Escape(probe.pos),
+                    path,
+                };
+            }
+        }
+
+        Prediction {
+            path,
+            end: PredictionEnd::Running,
+        }
+    }
+
+    // Everything update_forces would push the probe with, apart from the magnetic field.
+    fn probe_acceleration(
+        &self,
+        frame: &Frame,
+        probe: &Probe,
+        fields: &[ForceField],
+        gravity: Gravity,
+    ) -> Vec2<f64, WorldSpace> {
+        let mut force = self.forces.electric_field * probe.charge;
+        for j in 0..frame.pos.len() {
+            let contact = frame.radius[j] + probe.radius;
+            if self.forces.gravity {
+                force += gravity_between(
+                    probe.pos,
+                    probe.mass,
+                    frame.pos[j],
+                    frame.mass[j],
+                    contact,
+                    gravity,
+                );
+            }
+            if self.forces.electrostatics && probe.charge != 0.0 && frame.charge[j] != 0.0 {
+                force -= gravity_between(
+                    probe.pos,
+                    probe.charge,
+                    frame.pos[j],
+                    frame.charge[j],
+                    contact,
+                    gravity,
+                );
+            }
+        }
+        for field in fields {
+            force += field.acceleration(probe.pos, probe.vel) * probe.mass;
+        }
+        force / probe.mass
+    }
+
+    fn probe_kick(
+        &self,
+        probe: &Probe,
+        acc: Vec2<f64, WorldSpace>,
+        dt: f64,
+    ) -> Vec2<f64, WorldSpace> {
+        let half = acc * (dt / 2.0);
+        let angle = -probe.charge / probe.mass * self.forces.magnetic_field * dt;
+        (probe.vel + half).rotate(angle) + half
+    }
+}
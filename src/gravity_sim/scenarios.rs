@@ -0,0 +1,542 @@
+use super::{
+    create_particle, create_particle_with_mass, emitters::Emitter, motion::Motion,
+    particles::Particles, sphere_mass, Particle,
+};
+use crate::utils::*;
+use core::f64;
+use num::pow::Pow;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenario {
+    TwoBodies,
+    SunEarthMoon,
+    Binary,
+    FigureEight,
+    GalacticDisk,
+    PlummerSphere,
+    PlanetesimalRing,
+    Orrery,
+}
+
+// A generated set of bodies, along with the units they were defined in.
+#[derive(Debug, Clone)]
+pub struct Scene {
+    pub particles: Particles,
+    pub units: UnitSystem,
+    pub softening: f64, // softening length, few body systems are left exact.
+    pub emitters: Vec<Emitter>,
+}
+
+impl Scenario {
+    pub const ALL: [Self; 8] = [
+        Self::TwoBodies,
+        Self::SunEarthMoon,
+        Self::Binary,
+        Self::FigureEight,
+        Self::GalacticDisk,
+        Self::PlummerSphere,
+        Self::PlanetesimalRing,
+        Self::Orrery,
+    ];
+
+    // Builds the scenario with its default parameters, centred on centre.
+    pub fn build(self, centre: Vec2<f64, WorldSpace>, seed: u64) -> Scene {
+        match self {
+            Self::TwoBodies => two_bodies(centre, &TwoBodiesParams::default(), seed),
+            Self::SunEarthMoon => sun_earth_moon(centre, &SunEarthMoonParams::default(), seed),
+            Self::Binary => binary(centre, &BinaryParams::default(), seed),
+            Self::FigureEight => figure_eight(centre, &FigureEightParams::default(), seed),
+            Self::GalacticDisk => galactic_disk(centre, &GalacticDiskParams::default(), seed),
+            Self::PlummerSphere => plummer_sphere(centre, &PlummerParams::default(), seed),
+            Self::PlanetesimalRing => {
+                planetesimal_ring(centre, &PlanetesimalRingParams::default(), seed)
+            }
+            Self::Orrery => orrery(centre, &OrreryParams::default(), seed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TwoBodiesParams {
+    pub radius: f64,
+    pub separation: f64,
+    pub angle: Option<f64>, // of the line between them, None == turned by the seed.
+}
+
+// SI, the scene picks its own units.
+#[derive(Debug, Clone, Copy)]
+pub struct SunEarthMoonParams {
+    pub earth_orbit: f64, // m
+    pub earth_speed: f64, // m/s
+    pub moon_orbit: f64,  // about the earth.
+    pub moon_speed: f64,  // relative to the earth.
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BinaryParams {
+    pub radius: f64,
+    pub separation: f64,   // at apoapsis
+    pub eccentricity: f64, // 0.0 == circular
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FigureEightParams {
+    pub radius: f64,
+    pub scale: f64, // world units per unit length of the Chenciner-Montgomery solution
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GalacticDiskParams {
+    pub count: usize,
+    pub core_radius: f64,
+    pub star_radius: f64,
+    pub disk_radius: f64,
+    pub velocity_dispersion: f64, // fraction of the circular velocity
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlummerParams {
+    pub count: usize,
+    pub star_radius: f64,
+    pub scale_radius: f64, // Plummer radius a, half the mass sits within ~1.3a
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlanetesimalRingParams {
+    pub count: usize,
+    pub star_radius: f64,
+    pub planetesimal_radius: f64,
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    pub eccentricity: f64, // maximum, sampled uniformly per body
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OrreryParams {
+    pub count: usize,
+    pub sun_radius: f64,
+    pub planet_radius: f64,
+    pub planet_orbit: f64,
+    pub asteroid_radius: f64,
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    pub stream_radius: f64, // where the emitter feeding the belt sits.
+    pub stream_cap: usize,
+}
+
+impl Default for TwoBodiesParams {
+    fn default() -> Self {
+        Self {
+            radius: 60.0,
+            separation: 200.0 * f64::consts::SQRT_2,
+            angle: Some(f64::consts::FRAC_PI_4), // the original diagonal layout.
+        }
+    }
+}
+
+impl Default for SunEarthMoonParams {
+    fn default() -> Self {
+        Self {
+            earth_orbit: 1.496e11,
+            earth_speed: 29_780.0,
+            moon_orbit: 3.844e8,
+            moon_speed: 1_022.0,
+        }
+    }
+}
+
+impl Default for BinaryParams {
+    fn default() -> Self {
+        Self {
+            radius: 15.0,
+            separation: 200.0,
+            eccentricity: 0.0,
+        }
+    }
+}
+
+impl Default for FigureEightParams {
+    fn default() -> Self {
+        Self {
+            radius: 6.0,
+            scale: 120.0,
+        }
+    }
+}
+
+impl Default for GalacticDiskParams {
+    fn default() -> Self {
+        Self {
+            count: 1500,
+            core_radius: 20.0,
+            star_radius: 1.0,
+            disk_radius: 180.0,
+            velocity_dispersion: 0.05,
+        }
+    }
+}
+
+impl Default for PlummerParams {
+    fn default() -> Self {
+        Self {
+            count: 1000,
+            star_radius: 1.0,
+            scale_radius: 40.0,
+        }
+    }
+}
+
+impl Default for PlanetesimalRingParams {
+    fn default() -> Self {
+        Self {
+            count: 800,
+            star_radius: 20.0,
+            planetesimal_radius: 1.0,
+            inner_radius: 80.0,
+            outer_radius: 160.0,
+            eccentricity: 0.02,
+        }
+    }
+}
+
+impl Default for OrreryParams {
+    fn default() -> Self {
+        Self {
+            count: 400,
+            sun_radius: 20.0,
+            planet_radius: 8.0,
+            planet_orbit: 200.0,
+            asteroid_radius: 1.0,
+            inner_radius: 80.0,
+            outer_radius: 170.0,
+            stream_radius: 320.0,
+            stream_cap: 50,
+        }
+    }
+}
+
+// region: Generators
+pub fn two_bodies(centre: Vec2<f64, WorldSpace>, params: &TwoBodiesParams, seed: u64) -> Scene {
+    let units = DEFAULT_UNITS;
+    let density = units.density_from_si(EARTH_DENSITY);
+    let angle = params.angle.unwrap_or_else(|| orientation(seed));
+    let offset = vec2(params.separation / 2.0, 0.0).rotate(angle);
+
+    Scene {
+        particles: [
+            create_particle(centre - offset, vec2(0.0, 0.0), params.radius, density),
+            create_particle(centre + offset, vec2(0.0, 0.0), params.radius, density),
+        ]
+        .into_iter()
+        .collect(),
+        units,
+        softening: 0.0,
+        emitters: Vec::new(),
+    }
+}
+
+// True scale, so the bodies are tiny and the moon sits under a pixel from the earth.
+pub fn sun_earth_moon(
+    centre: Vec2<f64, WorldSpace>,
+    params: &SunEarthMoonParams,
+    seed: u64,
+) -> Scene {
+    const MOON_MASS: f64 = 7.342e22;
+    const MOON_RADIUS: f64 = 1_737_400.0;
+
+    // 1 unit = 1e6 km, 1 unit mass = 1e24 kg, 1 unit time = 1 day.
+    let units = UnitSystem::new(1e9, 1e24, 86_400.0);
+
+    let angle = orientation(seed);
+    let earth_pos = centre + vec2(units.distance_from_si(params.earth_orbit), 0.0).rotate(angle);
+    let earth_vel = vec2(0.0, -units.speed_from_si(params.earth_speed)).rotate(angle);
+    let moon_pos = earth_pos + vec2(units.distance_from_si(params.moon_orbit), 0.0).rotate(angle);
+    let moon_vel = earth_vel + vec2(0.0, -units.speed_from_si(params.moon_speed)).rotate(angle);
+
+    let sun = create_particle_with_mass(
+        centre,
+        vec2(0.0, 0.0),
+        units.distance_from_si(SUN_RADIUS),
+        units.mass_from_si(SUN_MASS),
+    );
+    let earth = create_particle_with_mass(
+        earth_pos,
+        earth_vel,
+        units.distance_from_si(EARTH_RADIUS),
+        units.mass_from_si(EARTH_MASS),
+    );
+    let moon = create_particle_with_mass(
+        moon_pos,
+        moon_vel,
+        units.distance_from_si(MOON_RADIUS),
+        units.mass_from_si(MOON_MASS),
+    );
+
+    Scene {
+        particles: [sun, earth, moon].into_iter().collect(),
+        units,
+        softening: 0.0,
+        emitters: Vec::new(),
+    }
+}
+
+pub fn binary(centre: Vec2<f64, WorldSpace>, params: &BinaryParams, seed: u64) -> Scene {
+    let units = DEFAULT_UNITS;
+    let density = units.density_from_si(EARTH_DENSITY);
+    let mass = sphere_mass(params.radius, density);
+
+    // circular speed of each body about the shared centre is sqrt(G m / 2a),
+    // scaled down so the given separation is the apoapsis.
+    let speed = f64::sqrt(units.grav_const() * mass / (2.0 * params.separation))
+        * f64::sqrt(1.0 - params.eccentricity);
+    let angle = orientation(seed);
+    let offset = vec2(params.separation / 2.0, 0.0).rotate(angle);
+    let vel = vec2(0.0, speed).rotate(angle);
+
+    Scene {
+        particles: [
+            create_particle(centre - offset, vel, params.radius, density),
+            create_particle(centre + offset, vel * -1.0, params.radius, density),
+        ]
+        .into_iter()
+        .collect(),
+        units,
+        softening: 0.0,
+        emitters: Vec::new(),
+    }
+}
+
+// Chenciner & Montgomery (2000), three equal masses chasing each other around a figure eight.
+pub fn figure_eight(centre: Vec2<f64, WorldSpace>, params: &FigureEightParams, seed: u64) -> Scene {
+    const POS: (f64, f64) = (0.970_004_36, -0.243_087_53);
+    const VEL: (f64, f64) = (-0.932_407_37, -0.864_731_46);
+
+    let units = DEFAULT_UNITS;
+    let density = units.density_from_si(EARTH_DENSITY);
+    let mass = sphere_mass(params.radius, density);
+    // the published solution has G = m = 1, rescale velocity for our G, m and length.
+    let speed_scale = f64::sqrt(units.grav_const() * mass / params.scale);
+
+    let angle = orientation(seed);
+    let pos = vec2(POS.0, POS.1).rotate(angle) * params.scale;
+    let vel = vec2(VEL.0, VEL.1).rotate(angle) * speed_scale;
+
+    Scene {
+        particles: [
+            create_particle(centre + pos, vel / -2.0, params.radius, density),
+            create_particle(centre - pos, vel / -2.0, params.radius, density),
+            create_particle(centre, vel, params.radius, density),
+        ]
+        .into_iter()
+        .collect(),
+        units,
+        softening: 0.0,
+        emitters: Vec::new(),
+    }
+}
+
+pub fn galactic_disk(
+    centre: Vec2<f64, WorldSpace>,
+    params: &GalacticDiskParams,
+    seed: u64,
+) -> Scene {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let units = DEFAULT_UNITS;
+    let density = units.density_from_si(EARTH_DENSITY);
+    let core_mass = sphere_mass(params.core_radius, density);
+    let star_mass = sphere_mass(params.star_radius, density);
+
+    let inner = params.core_radius * 1.5;
+    let mut particles = Particles::with_capacity(params.count + 1);
+    particles.push(create_particle(
+        centre,
+        vec2(0.0, 0.0),
+        params.core_radius,
+        density,
+    ));
+
+    // radii uniform in area, so the disk has a constant surface density.
+    let mut radii: Vec<f64> = (0..params.count)
+        .map(|_| f64::sqrt(rng.gen_range(inner.pow(2)..params.disk_radius.pow(2))))
+        .collect();
+    radii.sort_by(f64::total_cmp);
+
+    for (i, radius) in radii.into_iter().enumerate() {
+        let angle = rng.gen_range(0.0..f64::consts::TAU);
+        let dir = vec2(angle.cos(), angle.sin());
+
+        // everything inside this orbit, treated as if it were spherically distributed.
+        let enclosed = core_mass + star_mass * i as f64;
+        let speed = f64::sqrt(units.grav_const() * enclosed / radius);
+        let dispersion = vec2(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0))
+            * (speed * params.velocity_dispersion);
+        let vel = vec2(dir.y, -dir.x) * speed + dispersion;
+
+        particles.push(create_particle(
+            centre + dir * radius,
+            vel,
+            params.star_radius,
+            density,
+        ));
+    }
+
+    Scene {
+        particles,
+        units,
+        softening: params.star_radius,
+        emitters: Vec::new(),
+    }
+}
+
+// Plummer (1911) sphere sampled in 3D (Aarseth, Henon & Wielen 1974), projected onto the plane.
+pub fn plummer_sphere(centre: Vec2<f64, WorldSpace>, params: &PlummerParams, seed: u64) -> Scene {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let units = DEFAULT_UNITS;
+    let density = units.density_from_si(EARTH_DENSITY);
+    let total_mass = sphere_mass(params.star_radius, density) * params.count as f64;
+    let a = params.scale_radius;
+
+    let mut particles = Particles::with_capacity(params.count);
+    while particles.len() < params.count {
+        // inverse of the cumulative mass profile, M(<r) = M r^3 / (r^2 + a^2)^(3/2)
+        let mass_fraction: f64 = rng.gen_range(1e-6..1.0);
+        let radius = a / f64::sqrt(mass_fraction.pow(-2.0 / 3.0) - 1.0);
+        if radius > a * 10.0 {
+            continue; // the tail is unbounded, drop outliers.
+        }
+
+        // rejection sample q = v / v_escape from g(q) = q^2 (1 - q^2)^3.5
+        let q = loop {
+            let q: f64 = rng.gen();
+            let g: f64 = rng.gen_range(0.0..0.1);
+            if g < q.pow(2) * f64::pow(1.0 - q.pow(2), 3.5) {
+                break q;
+            }
+        };
+        let escape_speed =
+            f64::sqrt(2.0 * units.grav_const() * total_mass / f64::sqrt(radius.pow(2) + a.pow(2)));
+
+        let pos = random_unit_sphere(&mut rng) * radius;
+        let vel = random_unit_sphere(&mut rng) * (q * escape_speed);
+        particles.push(create_particle(
+            centre + pos,
+            vel,
+            params.star_radius,
+            density,
+        ));
+    }
+
+    Scene {
+        particles,
+        units,
+        softening: params.star_radius,
+        emitters: Vec::new(),
+    }
+}
+
+pub fn planetesimal_ring(
+    centre: Vec2<f64, WorldSpace>,
+    params: &PlanetesimalRingParams,
+    seed: u64,
+) -> Scene {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let units = DEFAULT_UNITS;
+    let density = units.density_from_si(EARTH_DENSITY);
+    let star_mass = sphere_mass(params.star_radius, density);
+
+    let mut particles = Particles::with_capacity(params.count + 1);
+    particles.push(create_particle(
+        centre,
+        vec2(0.0, 0.0),
+        params.star_radius,
+        density,
+    ));
+
+    for _ in 0..params.count {
+        let radius = rng.gen_range(params.inner_radius..params.outer_radius);
+        let angle = rng.gen_range(0.0..f64::consts::TAU);
+        let dir = vec2(angle.cos(), angle.sin());
+
+        // starting at apoapsis, e in [0, max) shaves speed off the circular orbit.
+        let eccentricity = rng.gen_range(0.0..=params.eccentricity);
+        let speed = f64::sqrt(units.grav_const() * star_mass / radius * (1.0 - eccentricity));
+
+        particles.push(create_particle(
+            centre + dir * radius,
+            vec2(dir.y, -dir.x) * speed,
+            params.planetesimal_radius,
+            density,
+        ));
+    }
+
+    Scene {
+        particles,
+        units,
+        softening: params.planetesimal_radius,
+        emitters: Vec::new(),
+    }
+}
+// Pinned sun & a planet on rails, the asteroids between them are the only free bodies.
+pub fn orrery(centre: Vec2<f64, WorldSpace>, params: &OrreryParams, seed: u64) -> Scene {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let units = DEFAULT_UNITS;
+    let density = units.density_from_si(EARTH_DENSITY);
+    let sun_mass = sphere_mass(params.sun_radius, density);
+    let circular_speed = |radius: f64| f64::sqrt(units.grav_const() * sun_mass / radius);
+
+    let mut particles = Particles::with_capacity(params.count + 2);
+    particles.push(Particle {
+        motion: Motion::Static,
+        ..create_particle(centre, vec2(0.0, 0.0), params.sun_radius, density)
+    });
+
+    let planet_pos = centre + vec2(params.planet_orbit, 0.0);
+    let planet_vel = vec2(0.0, -circular_speed(params.planet_orbit));
+    particles.push(Particle {
+        motion: Motion::orbit(centre, planet_pos, planet_vel),
+        ..create_particle(planet_pos, planet_vel, params.planet_radius, density)
+    });
+
+    for _ in 0..params.count {
+        let radius = rng.gen_range(params.inner_radius..params.outer_radius);
+        let angle = rng.gen_range(0.0..f64::consts::TAU);
+        let dir = vec2(angle.cos(), angle.sin());
+
+        particles.push(create_particle(
+            centre + dir * radius,
+            vec2(dir.y, -dir.x) * circular_speed(radius),
+            params.asteroid_radius,
+            density,
+        ));
+    }
+
+    // a stream launched tangentially below circular speed, falling in across the planet's orbit.
+    let stream_pos = centre + vec2(-params.stream_radius, 0.0);
+    let speed = circular_speed(params.stream_radius);
+    let mut stream = Emitter::new(stream_pos, f64::consts::FRAC_PI_2, seed);
+    stream.speed = (0.6 * speed, 0.8 * speed);
+    stream.radius = (params.asteroid_radius, params.asteroid_radius);
+    stream.cap = params.stream_cap;
+
+    Scene {
+        particles,
+        units,
+        softening: params.asteroid_radius,
+        emitters: vec![stream],
+    }
+}
+// endregion
+
+// The few body layouts are exact, the seed only turns them about the centre.
+fn orientation(seed: u64) -> f64 {
+    StdRng::seed_from_u64(seed).gen_range(0.0..f64::consts::TAU)
+}
+
+// x, y of a uniformly distributed point on the unit sphere.
+fn random_unit_sphere(rng: &mut StdRng) -> Vec2<f64, WorldSpace> {
+    let z: f64 = rng.gen_range(-1.0..1.0);
+    let angle = rng.gen_range(0.0..f64::consts::TAU);
+    let xy = f64::sqrt(1.0 - z * z);
+    vec2(xy * angle.cos(), xy * angle.sin())
+}
@@ -0,0 +1,86 @@
+use super::{lerp, particles::Particles, GravitySim, ParticleSlices};
+use crate::utils::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// Recent world positions of every particle, keyed by id so culling & merging don't mix them up.
+// World space, so the camera can pan freely underneath them.
+#[derive(Debug, Clone)]
+pub struct Trails {
+    pub length: usize, // points kept per particle, 0 == off.
+    pub fade: f64,     // brightness left at the oldest point, 1.0 == no fade.
+    pub colour: Rgba,
+    history: HashMap<u64, VecDeque<Vec2<f64, WorldSpace>>>,
+}
+
+impl Trails {
+    pub fn new() -> Self {
+        Self {
+            length: TRAIL_LENGTHS[0],
+            fade: TRAIL_FADES[0],
+            colour: TRAIL_COLOURS[0],
+            history: HashMap::new(),
+        }
+    }
+
+    // Appends where each particle is drawn this frame, alpha as in render_particles.
+    pub fn record(&mut self, particles: &Particles, alpha: f64) {
+        optick::event!("Trails::record");
+
+        if self.length == 0 {
+            self.history.clear();
+            return;
+        }
+
+        let ParticleSlices {
+            id, pos, prev_pos, ..
+        } = particles.slices();
+        // merged, escaped & quarantined particles take their trail with them.
+        let live: HashSet<u64> = id.iter().copied().collect();
+        self.history.retain(|id, _| live.contains(id));
+
+        for ((&id, &pos), &prev_pos) in id.iter().zip(pos).zip(prev_pos) {
+            let pos = lerp(prev_pos, pos, alpha);
+            let trail = self.history.entry(id).or_default();
+            // paused or at rest, a repeated point would only eat into the length.
+            if trail.back() == Some(&pos) {
+                continue;
+            }
+            trail.push_back(pos);
+            while trail.len() > self.length {
+                trail.pop_front();
+            }
+        }
+    }
+
+    // Ids are reused after a clear, stale trails would otherwise attach to new particles.
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+}
+
+impl GravitySim {
+    pub fn render_trails(&mut self) {
+        optick::event!("Rendering Trails");
+
+        let camera = self.camera;
+        let simulation = &self.simulation;
+        let trails = &self.trails;
+
+        for trail in trails.history.values() {
+            let segments = trail.len().saturating_sub(1);
+            for (k, (&from, &to)) in trail.iter().zip(trail.iter().skip(1)).enumerate() {
+                if simulation.draws_across_wrap(from, to) {
+                    continue;
+                }
+                // oldest segment first, brightening towards the particle.
+                let brightness =
+                    trails.fade + (1.0 - trails.fade) * (k + 1) as f64 / segments as f64;
+                let colour = DGRAY.lerp(trails.colour, brightness);
+
+                Shape::draw_line(camera.to_pixel(from), camera.to_pixel(to), &mut |x, y| {
+                    self.plot_clipped(vec2(x, y), colour)
+                });
+            }
+        }
+    }
+}
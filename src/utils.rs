@@ -8,8 +8,10 @@ use rand::distributions::uniform::UniformDuration;
 use std::{
     cell::UnsafeCell,
     fmt,
+    hash::Hasher,
     marker::PhantomData,
     ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign},
+    time::Duration,
 };
 use wgpu::hal::auxil::db::intel::DEVICE_SKY_LAKE_MASK;
 use winit::dpi::{PhysicalPosition, PhysicalSize};
@@ -19,6 +21,9 @@ pub const GREEN: Rgba = Rgba::from_rgb(40, 255, 40);
 pub const WHITE: Rgba = Rgba::from_rgb(255, 255, 255);
 pub const DGRAY: Rgba = Rgba::from_rgb(44, 44, 44);
 pub const RED: Rgba = Rgba::from_rgb(255, 40, 40);
+pub const YELLOW: Rgba = Rgba::from_rgb(255, 220, 40);
+pub const GRAY: Rgba = Rgba::from_rgb(90, 90, 90);
+pub const BLUE: Rgba = Rgba::from_rgb(90, 140, 255);
 
 // Generic Parameters (*)
 pub const INIT_TITLE: &str = "Gravity Sim";
@@ -26,6 +31,7 @@ pub const INIT_WIDTH: u32 = 1600;
 pub const INIT_HEIGHT: u32 = 1200;
 pub const INIT_SCALE: u32 = 3;
 pub const INIT_DRAW_SIZE: i32 = 8;
+pub const INIT_SEED: u64 = 0; // shared by every generator, scenarios & soups
 pub const SIM_MAX_SCALE: u32 = 10;
 pub const MAX_DRAW_SIZE: i32 = 500;
 
@@ -34,56 +40,102 @@ pub const MOUSE_HOLD_THRESHOLD_MS: u64 = 250;
 pub const MOUSE_PRESS_COOLDOWN_MS: u64 = 100;
 pub const MOUSE_DRAG_THRESHOLD_PX: f64 = 5.0; // TODO(TOM): vary with dpi
 pub const KEY_COOLDOWN_MS: u64 = 100;
+pub const SCROLL_PIXELS_PER_LINE: f64 = 40.0; // touchpads scroll in pixels, wheels in lines
 pub const TARGET_FPS: f64 = 120.0;
 pub const FRAME_TIME_MS: f64 = 1000.0 / TARGET_FPS;
 pub const MS_BUFFER: f64 = 3.0;
+pub const SIM_STEPS_PER_SECOND: u64 = 120;
+pub const SIM_TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / SIM_STEPS_PER_SECOND);
+pub const MAX_STEPS_PER_FRAME: u32 = 8; // spiral of death guard, drop sim time past this.
+pub const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+// cell_sim.rs
+pub const SOUP_DENSITY: f64 = 0.3; // chance of a cell starting alive
 
 // gravity_sim.rs
 pub const MOUSE_DRAWBACK_MULTIPLIER: f64 = 10.0;
 pub const CAMERA_RESISTANCE: f64 = 0.97;
 pub const CAMERA_SPEED: f64 = 0.1;
+pub const CAMERA_ZOOM_STEP: f64 = 1.1; // per scroll wheel line
+pub const CAMERA_MIN_ZOOM: f64 = 0.01;
+pub const CAMERA_MAX_ZOOM: f64 = 100.0;
 
 pub const SMALL_VALUE: f64 = 1e-6;
 pub const COLLISION_RESTITUTION: f64 = 0.8;
-pub const PHYSICS_MULTIPLIER: f64 = 1e-12;
-pub const PHYSICS_RESISTANCE: f64 = 0.999;
-
-// SIM CONSTANTS
-pub const DISTANCE_SCALE: f64 = 1.1970456e+15; // pixel to meters conversion scale. (not logarithmic!)
-
-pub const GRAV_CONST: f64 = 6.6743e-11;
-pub const EARTH_MASS: f64 = 5.972e24;
-pub const EARTH_DENSITY: f64 = 5514.0 * DISTANCE_SCALE;
-pub const SUN_MASS: f64 = 1.989e30;
+pub const COLLISION_FRICTION: f64 = 0.2;
+pub const SPATIAL_HASH_MAX_SPAN: i64 = 8; // cells across, bigger particles skip the grid & are tested against everything
+pub const RESTITUTION_STEP: f64 = 0.1;
+pub const SIM_TIME_SCALE: f64 = 100.0; // world time units per real second
+pub const BARNES_HUT_THETA: f64 = 0.5;
+pub const BARNES_HUT_THETA_STEP: f64 = 0.1;
+pub const BARNES_HUT_MAX_THETA: f64 = 2.0;
+pub const GRAVITY_SOFTENING: f64 = 0.0; // world units, 0.0 == exact newtonian
+pub const ENCOUNTER_ACCURACY: f64 = 0.2; // substep length against sqrt(length / |acc|) during close encounters
+pub const ENCOUNTER_MAX_SUBSTEPS: usize = 64; // a step is split at most this many times, however close the encounter
+pub const SOFTENING_STEP: f64 = 0.25;
+pub const QUARANTINE_CAPACITY: usize = 64; // most recent non-finite particles kept for Shift+G
+pub const WORLD_WIDTH: f64 = 1600.0; // world units, walls & wrapping
+pub const WORLD_HEIGHT: f64 = 1200.0;
+pub const ESCAPE_RADIUS: f64 = 2000.0; // from the world centre, open boundary culls beyond this in the sandbox
+pub const ESCAPE_RADIUS_SCALE: f64 = 10.0; // scenarios cull beyond this many times their initial bounding radius
+pub const DIAGNOSTICS_HISTORY: usize = 1024; // steps kept in the ring buffer
+pub const DIAGNOSTICS_LOG_INTERVAL: usize = 120;
+pub const PREDICTION_STEPS: [usize; 5] = [0, 250, 500, 1000, 2000]; // 0 == off
+pub const PREDICTION_DASH: usize = 4; // pixels drawn, then skipped, along the predicted path
+pub const PREDICTION_PAIR_BUDGET: usize = 2_000_000; // pair interactions per frame spent recording the background of a prediction
+pub const SELECT_RADIUS_PX: f64 = 10.0; // right clicks further than this from every particle deselect
+pub const SELECT_MARGIN_PX: i32 = 3; // gap between a selected particle and its highlight ring
+pub const GRAB_THROW_WINDOW_MS: u64 = 100; // cursor history a thrown particle takes its velocity from
+pub const CHARGE_PER_MASS: [f64; 5] = [0.0, 1.0, -1.0, 10.0, -10.0]; // spawned charge, 1.0 == repels as hard as gravity pulls
+pub const ELECTRIC_FIELDS: [f64; 4] = [0.0, 1e-5, 1e-4, 1e-3]; // uniform field strength along +x, force per unit charge
+pub const MAGNETIC_FIELDS: [f64; 5] = [0.0, 0.01, 0.05, -0.01, -0.05]; // out of the screen, radians per time unit at q == m
+pub const CONSTRAINT_ITERATIONS: usize = 8; // rod projection passes per step
+pub const LATTICE_SIZES: [usize; 3] = [4, 8, 12]; // particles along each side of a brushed lattice
+pub const LATTICE_SPACING: f64 = 3.0; // radii between neighbouring lattice particles
+pub const SPRING_STIFFNESS: f64 = 0.05; // per unit mass, squared natural frequency of a single spring
+pub const SPRING_DAMPING: f64 = 0.02; // per unit mass
+pub const SPRING_BREAK_STRAIN: f64 = 1.0; // jelly springs snap at twice their rest length
+pub const ROD_BREAK_STRAIN: f64 = 0.05; // truss rods snap when a step stretches them this far
+pub const FIELD_GRAVITY: f64 = 5e-3; // uniform downward acceleration
+pub const FIELD_ATTRACTION: f64 = 5e-3; // acceleration at FIELD_RANGE
+pub const FIELD_SWIRL: f64 = 2e-3; // tangential acceleration at FIELD_RANGE
+pub const FIELD_DRAG: f64 = 1e-2; // per time unit, linear drag halves speed in ~70 time units
+pub const FIELD_RANGE: f64 = 100.0; // world units, point fields have their full strength here
+pub const FIELD_CORE_RADIUS: f64 = 10.0; // point field falloff stops growing inside this
+pub const FIELD_STRENGTH_STEP: f64 = 1.5;
+pub const EMITTER_RATE: f64 = 0.1; // particles per time unit
+pub const EMITTER_SPEED: (f64, f64) = (0.4, 0.8); // launch speed range
+pub const EMITTER_RADIUS: (f64, f64) = (1.0, 2.0); // particle radius range
+pub const EMITTER_SPREAD: f64 = std::f64::consts::PI / 12.0; // half angle of the launch cone
+pub const EMITTER_CAP: usize = 200; // live particles per emitter
+pub const EMITTER_ARROW_PX: f64 = 20.0;
+pub const EMITTER_SAVE_PATH: &str = "emitters.txt"; // placed emitters, Digit0 saves & Shift+Digit0 loads
+pub const INSPECTOR_SCALE_STEP: f64 = 1.25; // mass, radius & speed edits multiply or divide by this
+pub const INSPECTOR_ROTATE_STEP: f64 = std::f64::consts::PI / 12.0; // velocity direction edits, radians
+pub const TRAIL_LENGTHS: [usize; 4] = [0, 32, 128, 512]; // points per particle, 0 == off
+pub const TRAIL_FADES: [f64; 3] = [0.0, 0.5, 1.0]; // brightness at the tail, 1.0 == no fade
+pub const TRAIL_COLOURS: [Rgba; 5] = [BLUE, WHITE, GREEN, YELLOW, RED];
+
+// SIM CONSTANTS (SI), converted into world units through a UnitSystem.
+pub const GRAV_CONST: f64 = 6.6743e-11; // m^3 kg^-1 s^-2
+pub const EARTH_MASS: f64 = 5.972e24; // kg
+pub const EARTH_DENSITY: f64 = 5514.0; // kg/m^3
+pub const EARTH_RADIUS: f64 = 6_371_000.0; // m
+pub const SUN_MASS: f64 = 1.989e30; // kg
+pub const SUN_DENSITY: f64 = 1408.0; // kg/m^3
+pub const SUN_RADIUS: f64 = 696_340_000.0; // m
 
 /*
     Particle Conversion to real world units -- to not spaz float precision
-    - pos: 1.0 ==  1e4 km
-    - vel: 1.0 == 1e3km/s pixels/frame >> 1e4 km/(1/TARGET_FPS) << 1000 times more than it should be (make grav const e-4 less)
-    - mass: 1.0 == 1e20 kg
-    - radius: 1.0 == 1e4 km
-
     - distance: 1.0 == 1e7 m (1e4 km)
     - mass: 1.0 == 1e20 kg
-    - velocity: 1.0 == (1e7 m) / 1.0s (calc per frame, but mult by dx to get seconds)
+    - time: 1.0 == 60 s, SIM_TIME_SCALE world time units pass per real second.
+    - velocity: 1.0 == 1e7 m / 60 s
 
-    // TLDR: e-11 grav const for m, e-14 for km, e-18 for 1000km
+    G and densities are rescaled into these units (UnitSystem::grav_const, density_from_si),
+    so orbital periods match reality for whatever the unit system says a world unit is.
 */
-
-// pub const DISTANCE_SCALE: f64 = 1e-7;
-// pub const MASS_SCALE: f64 = 1e-20;
-// // pub const VELOCITY_SCALE: f64 = 1e3;
-// pub const DENSITY_SCALE: f64 = MASS_SCALE / (DISTANCE_SCALE * DISTANCE_SCALE * DISTANCE_SCALE);
-
-// pub const SUN_DENSITY: f64 = 1403.0 * DENSITY_SCALE; // kg/m3 --> val * 1e-20 * (1e7)^3
-// * DENSITY_SCALE;
-// pub const SUN_RADIUS: f64 = 696_340_000.0 * DISTANCE_SCALE; //69.634;
-// pub const EARTH_RADIUS: f64 = 6_378_000.0 * DISTANCE_SCALE; //6.371;
-
-// pub const SUN_DENSITY: f64 = 1403.0; // kg/m3 --> val * 1e-20 * (1e7)^3
-// pub const EARTH_DENSITY: f64 = 5514.0;
-// pub const SUN_RADIUS: f64 = 696_340_000.0; //69.634;
-// pub const EARTH_RADIUS: f64 = 6_378_000.0; //6.371;
+pub const DEFAULT_UNITS: UnitSystem = UnitSystem::new(1e7, 1e20, 60.0);
 
 // region: Vec2
 pub trait CoordSpace {}
@@ -98,6 +150,7 @@ macro_rules! create_coordinate_space {
 create_coordinate_space!(ScreenSpace); // Space of the window e.g. 720x480
 create_coordinate_space!(RenderSpace); // Space of the simulation e.g. 360x240
 create_coordinate_space!(WorldSpace); // Space of the world, any number, could be offscreen!
+create_coordinate_space!(SiSpace); // Space of the world in metres, see UnitSystem
 create_coordinate_space!(Unknown);
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -203,6 +256,22 @@ impl<T: fmt::Debug + Num + Copy + NumCast, U: CoordSpace> Vec2<T, U> {
     }
 }
 
+impl<U: CoordSpace> Vec2<f64, U> {
+    pub fn dot(self, rhs: Self) -> f64 {
+        self.x * rhs.x + self.y * rhs.y
+    }
+
+    pub fn length(self) -> f64 {
+        f64::sqrt(self.x * self.x + self.y * self.y)
+    }
+
+    // radians, from the x axis towards the y axis.
+    pub fn rotate(self, angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        vec2(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+}
+
 macro_rules! impl_vec2_op {
     ($op_name:ident) => {
         paste! {
@@ -247,6 +316,76 @@ impl_vec2_op!(Sub);
 impl_vec2_op!(Mul);
 impl_vec2_op!(Div);
 // endregion
+// region: Units
+// How one world unit of distance, mass and time maps onto SI.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitSystem {
+    pub metres: f64,
+    pub kilograms: f64,
+    pub seconds: f64,
+}
+
+impl UnitSystem {
+    pub const fn new(metres: f64, kilograms: f64, seconds: f64) -> Self {
+        Self {
+            metres,
+            kilograms,
+            seconds,
+        }
+    }
+
+    // G in world units, m^3 kg^-1 s^-2 -> world^3 mass^-1 time^-2
+    pub fn grav_const(&self) -> f64 {
+        GRAV_CONST * self.kilograms * self.seconds.pow(2) / self.metres.pow(3)
+    }
+
+    pub fn distance_to_si(&self, distance: f64) -> f64 {
+        distance * self.metres
+    }
+    pub fn distance_from_si(&self, metres: f64) -> f64 {
+        metres / self.metres
+    }
+
+    pub fn mass_to_si(&self, mass: f64) -> f64 {
+        mass * self.kilograms
+    }
+    pub fn mass_from_si(&self, kilograms: f64) -> f64 {
+        kilograms / self.kilograms
+    }
+
+    pub fn time_to_si(&self, time: f64) -> f64 {
+        time * self.seconds
+    }
+    pub fn time_from_si(&self, seconds: f64) -> f64 {
+        seconds / self.seconds
+    }
+
+    pub fn speed_to_si(&self, speed: f64) -> f64 {
+        speed * self.metres / self.seconds
+    }
+    pub fn speed_from_si(&self, metres_per_second: f64) -> f64 {
+        metres_per_second * self.seconds / self.metres
+    }
+
+    pub fn density_from_si(&self, kg_per_m3: f64) -> f64 {
+        kg_per_m3 * self.metres.pow(3) / self.kilograms
+    }
+
+    pub fn pos_to_si(&self, pos: Vec2<f64, WorldSpace>) -> Vec2<f64, SiSpace> {
+        pos.map(|n| self.distance_to_si(n)).cast_unit()
+    }
+    pub fn pos_from_si(&self, pos: Vec2<f64, SiSpace>) -> Vec2<f64, WorldSpace> {
+        pos.map(|n| self.distance_from_si(n)).cast_unit()
+    }
+
+    pub fn vel_to_si(&self, vel: Vec2<f64, WorldSpace>) -> Vec2<f64, SiSpace> {
+        vel.map(|n| self.speed_to_si(n)).cast_unit()
+    }
+    pub fn vel_from_si(&self, vel: Vec2<f64, SiSpace>) -> Vec2<f64, WorldSpace> {
+        vel.map(|n| self.speed_from_si(n)).cast_unit()
+    }
+}
+// endregion
 // region: Shape
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -360,6 +499,49 @@ impl Shape {
         }
     }
 
+    // Circle around centre, only visiting rows & spans inside [0, bounds), so a body zoomed far
+    // past the edges costs no more than the screen it covers. Outlines are one pixel wide.
+    pub fn draw_circle_clipped<T: CoordSpace>(
+        centre: Vec2<i32, T>,
+        radius: i32,
+        fill: bool,
+        bounds: Vec2<i32, T>,
+        mut plot: impl FnMut(i32, i32),
+    ) {
+        // half width of the row dy away from the centre, None past the top & bottom.
+        let half_width = |dy: i32| {
+            let dy = dy.abs() as i64;
+            let radius = radius as i64;
+            (dy <= radius).then(|| ((radius * radius - dy * dy) as f64).sqrt() as i32)
+        };
+        let mut span = |y: i32, from: i32, to: i32| {
+            for x in from.max(0)..=to.min(bounds.x - 1) {
+                plot(x, y);
+            }
+        };
+
+        let rows = centre.y.saturating_sub(radius).max(0)
+            ..centre
+                .y
+                .saturating_add(radius)
+                .saturating_add(1)
+                .min(bounds.y);
+        for y in rows {
+            let dy = y - centre.y;
+            let Some(width) = half_width(dy) else {
+                continue;
+            };
+            if fill {
+                span(y, centre.x - width, centre.x + width);
+            } else {
+                // from just past the next row out, so steep parts of the ring have no gaps.
+                let inner = half_width(dy.abs() + 1).map_or(0, |w| w + 1).min(width);
+                span(y, centre.x + inner, centre.x + width);
+                span(y, centre.x - width, centre.x - inner);
+            }
+        }
+    }
+
     pub fn draw_arrow<T: CoordSpace + Copy>(
         start: Vec2<i32, T>,
         end: Vec2<i32, T>,
@@ -419,6 +601,17 @@ impl Rgba {
         Self { r, g, b, a }
     }
 
+    // t == 0.0 is self, t == 1.0 is to.
+    pub fn lerp(self, to: Self, t: f64) -> Self {
+        let mix = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * t).round() as u8;
+        Self {
+            r: mix(self.r, to.r),
+            g: mix(self.g, to.g),
+            b: mix(self.b, to.b),
+            a: mix(self.a, to.a),
+        }
+    }
+
     pub const fn from_u32(colour: u32) -> Self {
         Self {
             r: ((colour >> 24) & 0xFF) as u8,
@@ -462,6 +655,86 @@ impl<T: Clone> Clone for SyncCell<T> {
     }
 }
 
+// FNV-1a, unlike std's hashers the output is fixed across runs, platforms and compiler versions.
+#[derive(Debug, Clone, Copy)]
+pub struct StateHasher(u64);
+
+impl StateHasher {
+    pub const fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    // bit pattern, so -0.0 != 0.0 and NaNs hash by payload, exactly what replay checks want.
+    pub fn write_f64(&mut self, n: f64) {
+        self.write_u64(n.to_bits());
+    }
+
+    pub fn write_vec2<U: CoordSpace>(&mut self, v: Vec2<f64, U>) {
+        self.write_f64(v.x);
+        self.write_f64(v.y);
+    }
+}
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StateHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    // the defaults use native byte order & pointer width, pinned so hashes match across machines.
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i16(&mut self, n: i16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_i128(&mut self, n: i128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_i64(n as i64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
 pub fn fmt_limited_precision<T: fmt::Debug>(x: T, format: &mut fmt::Formatter) -> fmt::Result {
     write!(format, "{x:.2?}") // Specify precision here
 }